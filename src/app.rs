@@ -1,4 +1,12 @@
+use crate::app::replay::{EventRecorder, Recording, ReplayQueue};
+use crate::app::smoothing::PointerSmoothing;
+use crate::app::timer::{TimerId, Timers};
+use crate::drawing::Rect;
+use crate::event::Modifiers;
+use crate::event::MouseButton;
 use crate::event::MouseClickEvent;
+use crate::event::MouseMoveEvent;
+use crate::event::PressState;
 use crate::window::WindowDims;
 use crate::{
 	drawing::{
@@ -6,11 +14,18 @@ use crate::{
 		DrawingBackend, SurfaceCreator,
 	},
 	window::{
+		any::{AnyBackend, AnyBackendError},
 		winit::{WinitBackend, WinitWindow},
 		WindowBackend, WindowEvent,
 	},
 };
-use std::collections::VecDeque;
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+pub mod replay;
+mod smoothing;
+pub mod timer;
 
 pub struct App<W: WindowBackend, D: DrawingBackend> {
 	pub window_backend: W,
@@ -18,53 +33,661 @@ pub struct App<W: WindowBackend, D: DrawingBackend> {
 	pub draw_backend: D,
 	evt_buf: VecDeque<WindowEvent>,
 	frame_dims: (f64, f64),
+	scale_factor: f64,
+	hit_regions: Vec<(u32, Rect)>,
 	last_hovered: Option<u32>,
+	input_state: InputState,
+	main_window_id: W::Id,
+	window_data: HashMap<W::Id, Box<dyn Any>>,
+	timers: Timers,
+	recording: Option<Recording>,
+	replay_queue: ReplayQueue,
+	pointer_smoothing: PointerSmoothing,
+	hidden: bool,
+	start_time: Instant,
+	last_frame: Option<Instant>,
+	delta_time: Duration,
 }
 
 impl<W: WindowBackend, D: DrawingBackend> App<W, D>
 where
-	W: SurfaceCreator<W, D>,
+	W: SurfaceCreator<W, D, Error = <W as WindowBackend>::Error>,
 {
 	pub fn new(title: &str, dims: WindowDims) -> Self {
-		let window_backend = W::init().unwrap();
-		let window = window_backend.create_window(title, dims).unwrap();
-		let surface = window_backend.create_surface(&window);
-		let draw_backend = D::new(surface);
-		let window_dims = window_backend.get_window_size(&window).unwrap();
+		Self::try_new(title, dims).unwrap()
+	}
 
-		App {
+	pub fn try_new(title: &str, dims: WindowDims) -> Result<Self, <W as WindowBackend>::Error> {
+		let window_backend = W::init()?;
+		let window = window_backend.create_window(title, dims)?;
+		let surface = window_backend.create_surface(&window)?;
+		let mut draw_backend = D::new(surface);
+		let scale_factor = window_backend.get_scale_factor(&window)?;
+		draw_backend.set_scale_factor(scale_factor);
+		let window_dims = window_backend.get_window_size(&window)?;
+		let main_window_id = window_backend.window_id(&window)?;
+
+		Ok(App {
 			window_backend,
 			window,
 			draw_backend,
 			evt_buf: VecDeque::new(),
 			frame_dims: (window_dims.0 as f64, window_dims.1 as f64),
+			scale_factor,
+			hit_regions: Vec::new(),
 			last_hovered: None,
-		}
+			input_state: InputState::default(),
+			main_window_id,
+			window_data: HashMap::new(),
+			timers: Timers::default(),
+			recording: None,
+			replay_queue: ReplayQueue::default(),
+			pointer_smoothing: PointerSmoothing::default(),
+			hidden: false,
+			start_time: Instant::now(),
+			last_frame: None,
+			delta_time: Duration::from_secs(0),
+		})
+	}
+
+	/// Shows a splash screen: an undecorated, always-on-top window of the given size centered on the primary monitor, drawn
+	/// once with `draw`. The window stays up until `SplashHandle::close` is called, and is redrawn whenever
+	/// `SplashHandle::update` sees it was exposed.
+	pub fn splash<F: FnMut(&mut D) + 'static>(
+		draw: F,
+		dims: (u32, u32),
+	) -> Result<SplashHandle<W, D>, <W as WindowBackend>::Error> {
+		let window_backend = W::init()?;
+		let window = window_backend.create_splash_window("", dims)?;
+		let surface = window_backend.create_surface(&window)?;
+		let draw_backend = D::new(surface);
+
+		let mut splash = SplashHandle {
+			window_backend,
+			window,
+			draw_backend,
+			draw: Box::new(draw),
+			evt_buf: VecDeque::new(),
+		};
+		splash.redraw();
+		Ok(splash)
 	}
 
-	pub fn poll_events<F: FnMut(WindowEvent)>(&mut self, mut f: F) {
+	/// Polls the window backend and dispatches the pending events to `f`. Events are delivered in a deterministic order:
+	/// real events from the window backend keep the order they arrived in, a synthetic release for every held mouse
+	/// button is inserted immediately before a `FocusLost` event, and events generated by the app (such as replayed
+	/// ones) follow the real ones, with synthetic resize and redraw events at the end of each poll.
+	pub fn poll_events<F: FnMut(WindowEvent)>(&mut self, f: F) {
 		self.window_backend.get_window_events(&mut self.window, &mut self.evt_buf);
-		while let Some(evt) = self.evt_buf.pop_front() {
+		self.dispatch_events(f);
+	}
+
+	/// Like `poll_events`, but sleeps until the window backend has an event or a timer or replayed event is due instead of
+	/// returning when there's nothing to do. Use this for programs that only redraw in response to input; animations
+	/// should keep using `poll_events`.
+	pub fn wait_events<F: FnMut(WindowEvent)>(&mut self, f: F) {
+		let deadline = match (self.timers.next_deadline(), self.replay_queue.next_due()) {
+			(Some(timer), Some(replay)) => Some(timer.min(replay)),
+			(timer, replay) => timer.or(replay),
+		};
+		self.window_backend
+			.wait_events_timeout(&mut self.window, &mut self.evt_buf, deadline);
+		self.dispatch_events(f);
+	}
+
+	/// Runs the app until its window is closed or `handler` returns `ControlFlow::Exit`, then closes the window. Each
+	/// iteration polls the pending events, passes them to `handler` one by one followed by a `Frame` event and presents
+	/// what the handler drew through `get_drawer`, so it should draw the whole frame on each `Frame` event. The loop
+	/// then sleeps for the rest of the frame so it runs at about 60 iterations per second. `CloseRequested` and
+	/// `CloseHappened` still reach the handler before the loop exits.
+	pub fn run<F: FnMut(&mut App<W, D>, WindowEvent) -> ControlFlow>(mut self, mut handler: F) {
+		const FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+		let mut events = Vec::new();
+		'run: loop {
+			let frame_start = Instant::now();
+			self.poll_events(|evt| events.push(evt));
+			for evt in events.drain(..) {
+				let closing = matches!(evt, WindowEvent::CloseRequested | WindowEvent::CloseHappened);
+				if handler(&mut self, evt) == ControlFlow::Exit || closing {
+					break 'run;
+				}
+			}
+			let delta = self.advance_frame(frame_start);
+			if handler(&mut self, WindowEvent::Frame { delta }) == ControlFlow::Exit {
+				break;
+			}
+			if self.is_renderable() {
+				self.draw_backend.present();
+				self.window_backend.present();
+			}
+			if let Some(remaining) = (frame_start + FRAME_INTERVAL).checked_duration_since(Instant::now()) {
+				std::thread::sleep(remaining);
+			}
+		}
+		self.close();
+	}
+
+	/// The time between the last two frames of `run`, or zero before the second frame.
+	pub fn delta_time(&self) -> Duration {
+		self.delta_time
+	}
+
+	/// The time since the app was created.
+	pub fn elapsed(&self) -> Duration {
+		self.start_time.elapsed()
+	}
+
+	/// Starts a new frame at `now`, returning the time since the previous one.
+	fn advance_frame(&mut self, now: Instant) -> Duration {
+		self.delta_time = self.last_frame.map(|last| now - last).unwrap_or_default();
+		self.last_frame = Some(now);
+		self.delta_time
+	}
+
+	fn dispatch_events<F: FnMut(WindowEvent)>(&mut self, mut f: F) {
+		let mut synthetic = VecDeque::new();
+		self.replay_queue.drain_due(Instant::now(), &mut synthetic);
+		self.input_state.order_events(&mut self.evt_buf, synthetic);
+		while let Some(mut evt) = self.evt_buf.pop_front() {
+			let mut hovered = self.last_hovered;
 			match evt {
+				WindowEvent::MouseMove(ref mut move_event) => {
+					move_event.pos = self.pointer_smoothing.smooth(Instant::now(), move_event.pos);
+					hovered = hit_test(&self.hit_regions, move_event.pos, self.scale_factor);
+				}
+				WindowEvent::MouseExit => hovered = None,
+				WindowEvent::MouseEnter => self.pointer_smoothing.reset(),
 				WindowEvent::ResizeHappened { dims } => {
-					self.draw_backend.resize_surface(dims);
+					if let Err(e) = self.draw_backend.resize_surface(dims) {
+						log::error!("Failed to resize drawing surface: {:?}", e);
+					}
 					self.frame_dims = dims;
 				}
+				WindowEvent::ScaleFactorChanged { scale } => {
+					self.draw_backend.set_scale_factor(scale);
+					self.scale_factor = scale;
+				}
+				WindowEvent::Shown => self.hidden = false,
+				WindowEvent::Hidden => self.hidden = true,
 				_ => {}
 			}
-			f(evt)
+			if let Some(recording) = self.recording.as_mut() {
+				recording.record(Instant::now(), &evt);
+			}
+			f(evt);
+			// Region events aren't recorded, since replaying the mouse moves brings them back
+			if hovered != self.last_hovered {
+				if let Some(id) = self.last_hovered {
+					f(WindowEvent::RegionExit(id));
+				}
+				if let Some(id) = hovered {
+					f(WindowEvent::RegionEnter(id));
+				}
+				self.last_hovered = hovered;
+			}
+		}
+		self.timers.run_due(Instant::now());
+	}
+
+	/// Starts recording the events dispatched by `poll_events`, replacing any recording in progress. The returned recorder
+	/// can be read at any time, and its events passed to `replay` to reproduce the session.
+	pub fn record(&mut self) -> EventRecorder {
+		let (recording, recorder) = Recording::start(Instant::now());
+		self.recording = Some(recording);
+		recorder
+	}
+
+	pub fn stop_recording(&mut self) {
+		self.recording = None;
+	}
+
+	/// Queues events to be dispatched by `poll_events` alongside the real ones. Each event is delayed relative to the
+	/// previous one, and the sequence starts after any events that are still queued from an earlier replay.
+	pub fn replay(&mut self, events: &[(Duration, WindowEvent)]) {
+		self.replay_queue.push(Instant::now(), events);
+	}
+
+	/// Schedules `f` to be called once from `poll_events` or `wait_events` after `after` has elapsed.
+	pub fn set_timeout<F: FnOnce() + 'static>(&mut self, after: Duration, f: F) -> TimerId {
+		self.timers.set_timeout(Instant::now(), after, f)
+	}
+
+	/// Schedules `f` to be called from `poll_events` or `wait_events` every `every` until the timer is cleared.
+	pub fn set_interval<F: FnMut() + 'static>(&mut self, every: Duration, f: F) -> TimerId {
+		self.timers.set_interval(Instant::now(), every, f)
+	}
+
+	pub fn clear_timer(&mut self, id: TimerId) {
+		self.timers.clear(id);
+	}
+
+	/// Smooths the positions of `MouseMove` events before they're delivered, to even out jitter in freehand drawing.
+	/// `factor` is the fraction of the previous position kept per 16 ms, so the result doesn't depend on how often the
+	/// pointer is sampled: 0.0 disables smoothing and values closer to 1.0 smooth more but lag further behind. Events are
+	/// timed when they're polled, so poll frequently for the best results.
+	pub fn set_pointer_smoothing(&mut self, factor: f64) {
+		self.pointer_smoothing.set_factor(factor);
+	}
+
+	/// Sets the areas of the window, in logical pixels, to track hovering over. Where regions overlap, the later one in the
+	/// slice wins, matching the order they would be drawn in. Whenever the hovered region changes, `RegionExit` and
+	/// `RegionEnter` events are delivered after the `MouseMove` that caused it. Hovering is only re-evaluated when the
+	/// pointer moves, so replacing the regions under a still pointer doesn't produce any events.
+	pub fn set_hit_regions(&mut self, regions: &[(u32, Rect)]) {
+		self.hit_regions = regions.to_vec();
+	}
+
+	/// The id of the hit region the pointer is currently over.
+	pub fn hovered(&self) -> Option<u32> {
+		self.last_hovered
+	}
+
+	/// The position of the last `MouseMove` event before smoothing was applied.
+	pub fn raw_pointer_position(&self) -> (f64, f64) {
+		self.pointer_smoothing.raw_pos()
+	}
+
+	/// Moves and if necessary shrinks the window so that it's fully visible within the work area of the nearest monitor.
+	pub fn clamp_to_work_area(&mut self) -> Result<(), <W as WindowBackend>::Error> {
+		let work_area = self.window_backend.get_work_area(&self.window)?;
+		let (x, y) = self.window_backend.get_window_position(&self.window)?;
+		let (width, height) = self.window_backend.get_window_size(&self.window)?;
+
+		let new_width = width.min(work_area.width);
+		let new_height = height.min(work_area.height);
+		if (new_width, new_height) != (width, height) {
+			self.window_backend.resize_by(
+				&self.window,
+				new_width as i32 - width as i32,
+				new_height as i32 - height as i32,
+			)?;
+		}
+
+		let new_x = x.max(work_area.x).min(work_area.x + (work_area.width - new_width) as i32);
+		let new_y = y.max(work_area.y).min(work_area.y + (work_area.height - new_height) as i32);
+		if (new_x, new_y) != (x, y) {
+			self.window_backend.move_by(&self.window, new_x - x, new_y - y)?;
 		}
+		Ok(())
+	}
+
+	/// The mouse buttons currently held down, which can be used to detect chords. The set is cleared when the window loses
+	/// focus.
+	pub fn pressed_buttons(&self) -> &HashSet<MouseButton> {
+		&self.input_state.pressed_buttons
+	}
+
+	pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+		self.input_state.pressed_buttons.contains(&button)
+	}
+
+	/// The id of the window the app was created with.
+	pub fn main_window_id(&self) -> W::Id {
+		self.main_window_id
+	}
+
+	/// Attaches arbitrary user state to a window, replacing any state previously attached to it.
+	pub fn set_window_data<T: Any>(&mut self, window: W::Id, data: T) {
+		self.window_data.insert(window, Box::new(data));
+	}
+
+	/// Gets the user state attached to a window, if there is any and it is of type `T`.
+	pub fn get_window_data<T: Any>(&self, window: W::Id) -> Option<&T> {
+		self.window_data.get(&window).and_then(|data| data.downcast_ref())
+	}
+
+	/// Gets mutable access to the user state attached to a window, if there is any and it is of type `T`.
+	pub fn get_window_data_mut<T: Any>(&mut self, window: W::Id) -> Option<&mut T> {
+		self.window_data.get_mut(&window).and_then(|data| data.downcast_mut())
+	}
+
+	/// Attaches arbitrary user state to the main window, like `set_window_data`.
+	pub fn set_main_window_data<T: Any>(&mut self, data: T) {
+		self.set_window_data(self.main_window_id, data);
+	}
+
+	/// Gets the user state attached to the main window, like `get_window_data`.
+	pub fn get_main_window_data<T: Any>(&self) -> Option<&T> {
+		self.get_window_data(self.main_window_id)
+	}
+
+	/// Gets mutable access to the user state attached to the main window, like `get_window_data_mut`.
+	pub fn get_main_window_data_mut<T: Any>(&mut self) -> Option<&mut T> {
+		self.get_window_data_mut(self.main_window_id)
 	}
 
 	pub fn get_drawer(&mut self) -> &mut D {
 		&mut self.draw_backend
 	}
 
+	/// Whether drawing would currently be visible. It isn't while the window is hidden (e.g. minimized) or has no area, in
+	/// which case `draw` and `present` skip their work until the window is shown or resized again.
+	pub fn is_renderable(&self) -> bool {
+		!self.hidden && self.frame_dims.0 >= 1.0 && self.frame_dims.1 >= 1.0
+	}
+
+	/// Draws a frame with `f` and presents it, or does nothing if the window isn't renderable.
+	pub fn draw<F: FnOnce(&mut D)>(&mut self, f: F) {
+		if !self.is_renderable() {
+			return;
+		}
+		f(&mut self.draw_backend);
+		self.draw_backend.present();
+		self.window_backend.present();
+	}
+
 	pub fn present(&self) {
+		if !self.is_renderable() {
+			return;
+		}
+		self.window_backend.present();
+	}
+
+	/// Enables or disables automatic damage tracking in the drawing backend, so that presenting only sends the areas that
+	/// were drawn to since the last present. With this enabled, redraw just what changed (including its background)
+	/// instead of clearing and redrawing the whole window.
+	pub fn auto_damage(&mut self, enabled: bool) {
+		self.draw_backend.set_damage_tracking(enabled);
+	}
+
+	pub fn close(self) {
+		// The surface has to be finished while its window still exists
+		drop(self.draw_backend);
+		self.window_backend.close(self.window);
+	}
+}
+
+/// Returned by the handler passed to `App::run` to keep the loop going or stop it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlFlow {
+	Continue,
+	Exit,
+}
+
+pub struct SplashHandle<W: WindowBackend, D: DrawingBackend> {
+	window_backend: W,
+	window: W::Window,
+	draw_backend: D,
+	draw: Box<dyn FnMut(&mut D)>,
+	evt_buf: VecDeque<WindowEvent>,
+}
+
+impl<W: WindowBackend, D: DrawingBackend> SplashHandle<W, D> {
+	/// Handles pending window events, redrawing the splash screen if it was exposed or resized.
+	pub fn update(&mut self) {
+		self.window_backend.get_window_events(&mut self.window, &mut self.evt_buf);
+		let mut needs_redraw = false;
+		while let Some(evt) = self.evt_buf.pop_front() {
+			match evt {
+				WindowEvent::ResizeHappened { dims } => {
+					if let Err(e) = self.draw_backend.resize_surface(dims) {
+						log::error!("Failed to resize drawing surface: {:?}", e);
+					}
+					needs_redraw = true;
+				}
+				WindowEvent::Expose { more_coming: false, .. } => needs_redraw = true,
+				_ => {}
+			}
+		}
+		if needs_redraw {
+			self.redraw();
+		}
+	}
+
+	fn redraw(&mut self) {
+		(self.draw)(&mut self.draw_backend);
+		self.draw_backend.present();
 		self.window_backend.present();
 	}
 
 	pub fn close(self) {
+		drop(self.draw_backend);
 		self.window_backend.close(self.window);
 	}
 }
+
+/// Opens an app with whichever window backend works on this system, trying XCB first and falling back to winit.
+pub fn open_default_app<D: DrawingBackend>(title: &str, dims: WindowDims) -> Result<App<AnyBackend, D>, AnyBackendError>
+where
+	AnyBackend: SurfaceCreator<AnyBackend, D, Error = AnyBackendError>,
+{
+	App::try_new(title, dims)
+}
+
+/// Finds the topmost (last) region containing `pos`. The regions are in logical pixels and `pos` is in physical ones, so
+/// it's scaled down by `scale` first.
+fn hit_test(regions: &[(u32, Rect)], pos: (f64, f64), scale: f64) -> Option<u32> {
+	let pos = (pos.0 / scale, pos.1 / scale);
+	regions.iter().rev().find(|(_, rect)| rect.contains(pos)).map(|&(id, _)| id)
+}
+
+#[test]
+fn hit_test_prefers_later_regions() {
+	let rect = |x, y, width, height| Rect { x, y, width, height };
+	let regions = [(1, rect(0.0, 0.0, 10.0, 10.0)), (2, rect(5.0, 5.0, 10.0, 10.0))];
+	assert_eq!(hit_test(&regions, (2.0, 2.0), 1.0), Some(1));
+	assert_eq!(hit_test(&regions, (7.0, 7.0), 1.0), Some(2));
+	assert_eq!(hit_test(&regions, (10.0, 2.0), 1.0), None);
+}
+
+#[test]
+fn hit_test_scales_pointer_to_logical_pixels() {
+	let rect = |x, y, width, height| Rect { x, y, width, height };
+	let regions = [(1, rect(0.0, 0.0, 10.0, 10.0))];
+	assert_eq!(hit_test(&regions, (18.0, 18.0), 2.0), Some(1));
+	assert_eq!(hit_test(&regions, (22.0, 2.0), 2.0), None);
+}
+
+/// Input state tracked across polls, used to synthesize events the backends don't deliver themselves.
+#[derive(Debug, Default)]
+struct InputState {
+	pressed_buttons: HashSet<MouseButton>,
+	cursor_pos: (f64, f64),
+	modifiers: Modifiers,
+}
+
+impl InputState {
+	/// Orders the events of one poll, where `events` holds the real events from the window backend and `synthetic` the
+	/// ones generated by the app.
+	fn order_events(&mut self, events: &mut VecDeque<WindowEvent>, synthetic: VecDeque<WindowEvent>) {
+		let (redraws, synthetic): (Vec<_>, Vec<_>) = synthetic.into_iter().partition(|evt| match evt {
+			WindowEvent::ResizeHappened { .. } | WindowEvent::Expose { .. } => true,
+			_ => false,
+		});
+		let mut ordered = VecDeque::with_capacity(events.len() + synthetic.len() + redraws.len());
+		for evt in events.drain(..).chain(synthetic) {
+			match evt {
+				WindowEvent::MouseMove(MouseMoveEvent { pos, .. }) => {
+					self.cursor_pos = pos;
+				}
+				WindowEvent::MouseClick(ref click_event) => {
+					self.cursor_pos = click_event.pos;
+					self.modifiers = click_event.modifiers;
+					match click_event.state {
+						PressState::Pressed => {
+							self.pressed_buttons.insert(click_event.button.clone());
+						}
+						PressState::Released => {
+							self.pressed_buttons.remove(&click_event.button);
+						}
+					}
+				}
+				WindowEvent::Keyboard(ref key_event) => {
+					self.modifiers = key_event.modifiers;
+				}
+				WindowEvent::FocusLost => {
+					for button in self.pressed_buttons.drain() {
+						ordered.push_back(WindowEvent::MouseClick(MouseClickEvent {
+							state: PressState::Released,
+							button,
+							pos: self.cursor_pos,
+							stylus: None,
+							modifiers: self.modifiers,
+						}));
+					}
+				}
+				_ => {}
+			}
+			ordered.push_back(evt);
+		}
+		ordered.extend(redraws);
+		*events = ordered;
+	}
+}
+
+#[cfg(test)]
+fn click(state: PressState, button: MouseButton) -> WindowEvent {
+	WindowEvent::MouseClick(MouseClickEvent {
+		state,
+		button,
+		pos: (1.0, 2.0),
+		stylus: None,
+		modifiers: Modifiers::default(),
+	})
+}
+
+#[cfg(test)]
+fn expose() -> WindowEvent {
+	WindowEvent::Expose {
+		region: crate::drawing::Rect {
+			x: 0.0,
+			y: 0.0,
+			width: 10.0,
+			height: 10.0,
+		},
+		more_coming: false,
+	}
+}
+
+#[test]
+fn real_events_keep_arrival_order() {
+	let mut input_state = InputState::default();
+	let mut events: VecDeque<_> = vec![
+		WindowEvent::MouseMove(MouseMoveEvent {
+			pos: (1.0, 2.0),
+			stylus: None,
+		}),
+		click(PressState::Pressed, MouseButton::Left),
+		WindowEvent::MouseEnter,
+		WindowEvent::ResizeHappened { dims: (10.0, 10.0) },
+		click(PressState::Released, MouseButton::Left),
+		expose(),
+	]
+	.into();
+	let expected: Vec<_> = events.iter().cloned().collect();
+	input_state.order_events(&mut events, VecDeque::new());
+	assert_eq!(events.into_iter().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn synthetic_resize_and_redraw_events_are_appended() {
+	let mut input_state = InputState::default();
+	let mut events: VecDeque<_> = vec![
+		click(PressState::Pressed, MouseButton::Left),
+		WindowEvent::ResizeHappened { dims: (10.0, 10.0) },
+		click(PressState::Released, MouseButton::Left),
+	]
+	.into();
+	let synthetic = vec![
+		expose(),
+		WindowEvent::ResizeHappened { dims: (20.0, 20.0) },
+		click(PressState::Pressed, MouseButton::Right),
+	]
+	.into();
+	input_state.order_events(&mut events, synthetic);
+	assert_eq!(
+		events.into_iter().collect::<Vec<_>>(),
+		vec![
+			click(PressState::Pressed, MouseButton::Left),
+			WindowEvent::ResizeHappened { dims: (10.0, 10.0) },
+			click(PressState::Released, MouseButton::Left),
+			click(PressState::Pressed, MouseButton::Right),
+			expose(),
+			WindowEvent::ResizeHappened { dims: (20.0, 20.0) },
+		]
+	);
+	assert!(input_state.pressed_buttons.contains(&MouseButton::Right));
+	assert!(!input_state.pressed_buttons.contains(&MouseButton::Left));
+}
+
+#[test]
+fn held_buttons_are_released_before_focus_lost() {
+	let mut input_state = InputState::default();
+	let mut events: VecDeque<_> = vec![
+		click(PressState::Pressed, MouseButton::Left),
+		click(PressState::Pressed, MouseButton::Right),
+		click(PressState::Released, MouseButton::Left),
+	]
+	.into();
+	input_state.order_events(&mut events, VecDeque::new());
+	assert!(input_state.pressed_buttons.contains(&MouseButton::Right));
+	assert!(!input_state.pressed_buttons.contains(&MouseButton::Left));
+
+	let mut events: VecDeque<_> = vec![expose(), WindowEvent::FocusLost].into();
+	input_state.order_events(&mut events, VecDeque::new());
+	assert_eq!(
+		events.into_iter().collect::<Vec<_>>(),
+		vec![
+			expose(),
+			click(PressState::Released, MouseButton::Right),
+			WindowEvent::FocusLost,
+		]
+	);
+	assert!(input_state.pressed_buttons.is_empty());
+}
+
+#[cfg(feature = "display-tests")]
+#[test]
+fn intervals_wake_wait_events() {
+	use crate::window::xcb::XcbBackend;
+	use std::cell::Cell;
+	use std::rc::Rc;
+
+	let mut app = App::<XcbBackend, CairoBackend>::new(
+		"intervals_wake_wait_events",
+		WindowDims {
+			x: 0,
+			y: 0,
+			width: 64,
+			height: 64,
+		},
+	);
+	let count = Rc::new(Cell::new(0));
+	let interval_count = count.clone();
+	app.set_interval(Duration::from_millis(10), move || {
+		interval_count.set(interval_count.get() + 1)
+	});
+
+	// Nothing happens to the window after it's mapped, so only the interval can end these waits
+	let start = Instant::now();
+	while count.get() < 5 {
+		app.wait_events(|_| {});
+		assert!(start.elapsed() < Duration::from_secs(5));
+	}
+	app.close();
+}
+
+#[cfg(feature = "display-tests")]
+#[test]
+fn apps_can_be_dropped_after_drawing() {
+	use crate::window::xcb::XcbBackend;
+
+	let dims = WindowDims {
+		x: 0,
+		y: 0,
+		width: 64,
+		height: 64,
+	};
+	// Dropping the app disconnects from the server, which mustn't happen before the surface is finished
+	let mut app = App::<XcbBackend, CairoBackend>::new("apps_can_be_dropped_after_drawing", dims);
+	app.draw(|cairo| {
+		cairo.rect(0.0, 0.0, 32.0, 32.0);
+		cairo.fill();
+	});
+	drop(app);
+
+	let mut app = App::<XcbBackend, CairoBackend>::new("apps_can_be_dropped_after_drawing", dims);
+	app.draw(|cairo| cairo.paint());
+	app.close();
+}
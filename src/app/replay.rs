@@ -0,0 +1,104 @@
+use crate::window::WindowEvent;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// A handle to a recording of the events dispatched by an `App`, started with `App::record`. Each event is stored with
+/// the delay since the previous event (or since the recording started for the first one), which is the format
+/// `App::replay` takes.
+#[derive(Clone)]
+pub struct EventRecorder {
+	events: Rc<RefCell<Vec<(Duration, WindowEvent)>>>,
+}
+
+impl EventRecorder {
+	/// Gets the events recorded so far.
+	pub fn events(&self) -> Vec<(Duration, WindowEvent)> {
+		self.events.borrow().clone()
+	}
+}
+
+pub(crate) struct Recording {
+	last_event: Instant,
+	events: Rc<RefCell<Vec<(Duration, WindowEvent)>>>,
+}
+
+impl Recording {
+	pub fn start(now: Instant) -> (Self, EventRecorder) {
+		let events = Rc::new(RefCell::new(Vec::new()));
+		(
+			Recording {
+				last_event: now,
+				events: events.clone(),
+			},
+			EventRecorder { events },
+		)
+	}
+
+	pub fn record(&mut self, now: Instant, evt: &WindowEvent) {
+		self.events.borrow_mut().push((now - self.last_event, evt.clone()));
+		self.last_event = now;
+	}
+}
+
+/// Events queued by `App::replay`, waiting for their time to be injected.
+#[derive(Default)]
+pub(crate) struct ReplayQueue {
+	queue: VecDeque<(Instant, WindowEvent)>,
+}
+
+impl ReplayQueue {
+	/// Queues events that are each delayed relative to the previous one, starting after any events already queued.
+	pub fn push(&mut self, now: Instant, events: &[(Duration, WindowEvent)]) {
+		let mut time = self.queue.back().map(|(time, _)| *time).unwrap_or(now).max(now);
+		for (delay, evt) in events {
+			time += *delay;
+			self.queue.push_back((time, evt.clone()));
+		}
+	}
+
+	/// The time the next queued event is due, if any are queued.
+	pub fn next_due(&self) -> Option<Instant> {
+		self.queue.front().map(|(time, _)| *time)
+	}
+
+	/// Moves the events that are due at `now` into `event_buf`.
+	pub fn drain_due(&mut self, now: Instant, event_buf: &mut VecDeque<WindowEvent>) {
+		while self.queue.front().map(|(time, _)| *time <= now).unwrap_or(false) {
+			let (_, evt) = self.queue.pop_front().unwrap();
+			event_buf.push_back(evt);
+		}
+	}
+}
+
+#[test]
+fn replayed_events_respect_delays() {
+	let expose = WindowEvent::Expose {
+		region: crate::drawing::Rect {
+			x: 0.0,
+			y: 0.0,
+			width: 10.0,
+			height: 10.0,
+		},
+		more_coming: false,
+	};
+	let start = Instant::now();
+	let mut queue = ReplayQueue::default();
+	queue.push(
+		start,
+		&[
+			(Duration::from_millis(0), WindowEvent::MouseEnter),
+			(Duration::from_millis(10), expose.clone()),
+			(Duration::from_millis(10), WindowEvent::MouseExit),
+		],
+	);
+
+	let mut event_buf = VecDeque::new();
+	queue.drain_due(start, &mut event_buf);
+	assert_eq!(event_buf.drain(..).collect::<Vec<_>>(), vec![WindowEvent::MouseEnter]);
+	queue.drain_due(start + Duration::from_millis(15), &mut event_buf);
+	assert_eq!(event_buf.drain(..).collect::<Vec<_>>(), vec![expose]);
+	queue.drain_due(start + Duration::from_millis(20), &mut event_buf);
+	assert_eq!(event_buf.drain(..).collect::<Vec<_>>(), vec![WindowEvent::MouseExit]);
+}
@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+/// The interval `PointerSmoothing::factor` is defined over, so that smoothing behaves the same no matter how often the
+/// pointer is sampled.
+const REFERENCE_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Exponential smoothing of pointer positions, applied to `MouseMove` events by `App::poll_events`.
+#[derive(Debug, Default)]
+pub(crate) struct PointerSmoothing {
+	/// The fraction of the previous smoothed position kept after `REFERENCE_INTERVAL`. 0.0 disables smoothing.
+	factor: f64,
+	last_sample: Option<(Instant, (f64, f64))>,
+	raw_pos: (f64, f64),
+}
+
+impl PointerSmoothing {
+	pub fn set_factor(&mut self, factor: f64) {
+		self.factor = factor.max(0.0).min(1.0);
+		self.reset();
+	}
+
+	/// Forgets the smoothed position, so the next sample is delivered as is. Used when the pointer re-enters the window
+	/// so it doesn't trail in from where it left.
+	pub fn reset(&mut self) {
+		self.last_sample = None;
+	}
+
+	pub fn raw_pos(&self) -> (f64, f64) {
+		self.raw_pos
+	}
+
+	pub fn smooth(&mut self, now: Instant, pos: (f64, f64)) -> (f64, f64) {
+		self.raw_pos = pos;
+		let smoothed = match self.last_sample {
+			Some((last_time, last_pos)) if self.factor > 0.0 => {
+				let elapsed = now.duration_since(last_time);
+				let intervals = (elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9)
+					/ (REFERENCE_INTERVAL.subsec_nanos() as f64 * 1e-9);
+				let keep = self.factor.powf(intervals);
+				(
+					last_pos.0 * keep + pos.0 * (1.0 - keep),
+					last_pos.1 * keep + pos.1 * (1.0 - keep),
+				)
+			}
+			_ => pos,
+		};
+		self.last_sample = Some((now, smoothed));
+		smoothed
+	}
+}
+
+#[test]
+fn smoothing_depends_on_elapsed_time() {
+	let start = Instant::now();
+	let mut smoothing = PointerSmoothing::default();
+	smoothing.set_factor(0.5);
+
+	assert_eq!(smoothing.smooth(start, (0.0, 0.0)), (0.0, 0.0));
+	assert_eq!(smoothing.smooth(start + REFERENCE_INTERVAL, (8.0, 4.0)), (4.0, 2.0));
+	// Two samples half an interval apart end up at the same place as one sample a whole interval later
+	let half = REFERENCE_INTERVAL / 2;
+	smoothing.smooth(start + REFERENCE_INTERVAL + half, (8.0, 4.0));
+	let pos = smoothing.smooth(start + REFERENCE_INTERVAL * 2, (8.0, 4.0));
+	assert!((pos.0 - 6.0).abs() < 1e-9 && (pos.1 - 3.0).abs() < 1e-9);
+	assert_eq!(smoothing.raw_pos(), (8.0, 4.0));
+
+	smoothing.set_factor(0.0);
+	assert_eq!(smoothing.smooth(start + REFERENCE_INTERVAL * 3, (1.0, 1.0)), (1.0, 1.0));
+}
@@ -0,0 +1,100 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+enum TimerCallback {
+	Once(Box<dyn FnOnce()>),
+	Repeating { every: Duration, f: Box<dyn FnMut()> },
+}
+
+struct Timer {
+	id: TimerId,
+	deadline: Instant,
+	callback: TimerCallback,
+}
+
+/// Timers scheduled on an `App`, fired from `App::poll_events` and `App::wait_events`.
+#[derive(Default)]
+pub(crate) struct Timers {
+	next_id: u64,
+	timers: Vec<Timer>,
+}
+
+impl Timers {
+	pub fn set_timeout<F: FnOnce() + 'static>(&mut self, now: Instant, after: Duration, f: F) -> TimerId {
+		self.add(now + after, TimerCallback::Once(Box::new(f)))
+	}
+
+	pub fn set_interval<F: FnMut() + 'static>(&mut self, now: Instant, every: Duration, f: F) -> TimerId {
+		self.add(now + every, TimerCallback::Repeating { every, f: Box::new(f) })
+	}
+
+	pub fn clear(&mut self, id: TimerId) {
+		self.timers.retain(|timer| timer.id != id);
+	}
+
+	/// The earliest time a timer is due, if any timers are scheduled.
+	pub fn next_deadline(&self) -> Option<Instant> {
+		self.timers.iter().map(|timer| timer.deadline).min()
+	}
+
+	/// Runs the callbacks of every timer that is due at `now`, in deadline order. Interval timers that fell behind by
+	/// more than one period are rescheduled relative to `now` instead of firing repeatedly to catch up.
+	pub fn run_due(&mut self, now: Instant) {
+		let (mut due, pending): (Vec<_>, Vec<_>) = self.timers.drain(..).partition(|timer| timer.deadline <= now);
+		self.timers = pending;
+		due.sort_by_key(|timer| timer.deadline);
+
+		for timer in due {
+			match timer.callback {
+				TimerCallback::Once(f) => f(),
+				TimerCallback::Repeating { every, mut f } => {
+					f();
+					let mut deadline = timer.deadline + every;
+					if deadline <= now {
+						deadline = now + every;
+					}
+					self.timers.push(Timer {
+						id: timer.id,
+						deadline,
+						callback: TimerCallback::Repeating { every, f },
+					});
+				}
+			}
+		}
+	}
+
+	fn add(&mut self, deadline: Instant, callback: TimerCallback) -> TimerId {
+		let id = TimerId(self.next_id);
+		self.next_id += 1;
+		self.timers.push(Timer { id, deadline, callback });
+		id
+	}
+}
+
+#[test]
+fn timers_fire_when_due() {
+	use std::cell::Cell;
+	use std::rc::Rc;
+
+	let start = Instant::now();
+	let mut timers = Timers::default();
+	let timeout_count = Rc::new(Cell::new(0));
+	let interval_count = Rc::new(Cell::new(0));
+
+	let count = timeout_count.clone();
+	timers.set_timeout(start, Duration::from_millis(10), move || count.set(count.get() + 1));
+	let count = interval_count.clone();
+	let interval = timers.set_interval(start, Duration::from_millis(5), move || count.set(count.get() + 1));
+
+	timers.run_due(start + Duration::from_millis(5));
+	assert_eq!((timeout_count.get(), interval_count.get()), (0, 1));
+	timers.run_due(start + Duration::from_millis(10));
+	assert_eq!((timeout_count.get(), interval_count.get()), (1, 2));
+	timers.run_due(start + Duration::from_millis(20));
+	assert_eq!((timeout_count.get(), interval_count.get()), (1, 3));
+
+	timers.clear(interval);
+	assert_eq!(timers.next_deadline(), None);
+}
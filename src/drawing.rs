@@ -1,9 +1,12 @@
 use crate::window::WindowBackend;
+use std::fmt::Debug;
 
 pub mod cairo;
 
 pub trait SurfaceCreator<W: WindowBackend, D: DrawingBackend> {
-	fn create_surface(&self, args: &W::Window) -> D::Surface;
+	type Error: Debug;
+
+	fn create_surface(&self, args: &W::Window) -> Result<D::Surface, Self::Error>;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -25,27 +28,285 @@ pub struct FontExtents {
 	pub max_y_advance: f64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+	pub x: f64,
+	pub y: f64,
+	pub width: f64,
+	pub height: f64,
+}
+
+impl Rect {
+	/// Whether `point` lies within the rectangle, including its top and left edges but not its bottom and right edges.
+	pub fn contains(&self, point: (f64, f64)) -> bool {
+		point.0 >= self.x && point.0 < self.x + self.width && point.1 >= self.y && point.1 < self.y + self.height
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+	pub r: f64,
+	pub g: f64,
+	pub b: f64,
+	pub a: f64,
+}
+
+impl Color {
+	pub fn rgba(r: f64, g: f64, b: f64, a: f64) -> Self {
+		Color { r, g, b, a }
+	}
+}
+
+/// A color gradient, usable as a drawing source. Stops are (offset, r, g, b, a), with offsets from 0.0 at the start of
+/// the gradient to 1.0 at its end.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gradient {
+	/// Varies along the line from `start` to `end`.
+	Linear {
+		start: (f64, f64),
+		end: (f64, f64),
+		stops: Vec<(f64, f64, f64, f64, f64)>,
+	},
+	/// Varies between the circle centered at `inner_center` with `inner_radius` and the one centered at `outer_center`
+	/// with `outer_radius`.
+	Radial {
+		inner_center: (f64, f64),
+		inner_radius: f64,
+		outer_center: (f64, f64),
+		outer_radius: f64,
+		stops: Vec<(f64, f64, f64, f64, f64)>,
+	},
+}
+
+/// A single element of a path, in user-space coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathElement {
+	MoveTo((f64, f64)),
+	LineTo((f64, f64)),
+	CurveTo((f64, f64), (f64, f64), (f64, f64)),
+	ClosePath,
+}
+
+/// How the color channels of loaded image data relate to its alpha channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaMode {
+	/// Color channels are independent of alpha, as in most image files.
+	Straight,
+	/// Color channels have already been multiplied by alpha.
+	Premultiplied,
+}
+
+/// A bitmap in tightly packed 8-bit RGBA pixels, row by row from the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageData {
+	pub pixels: Vec<u8>,
+	pub width: u32,
+	pub height: u32,
+	pub alpha_mode: AlphaMode,
+}
+
+/// How the ends of stroked lines are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+	/// Stops exactly at the end point.
+	Butt,
+	/// Rounds the end with a half circle centered on the end point.
+	Round,
+	/// Extends past the end point by half the line width.
+	Square,
+}
+
+/// How the corners between stroked segments are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineJoin {
+	Miter,
+	Round,
+	Bevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontSlant {
+	Normal,
+	Italic,
+	Oblique,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontWeight {
+	Normal,
+	Bold,
+}
+
+/// The order of the color sub-pixels of a display, used for sub-pixel text antialiasing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubpixelOrder {
+	Rgb,
+	Bgr,
+	Vrgb,
+	Vbgr,
+	/// Don't use sub-pixel antialiasing.
+	None,
+}
+
 pub trait DrawingBackend: Sized + 'static {
 	type Surface;
+	type Error: Debug;
 
 	fn new(surface: Self::Surface) -> Self;
 
-	fn resize_surface(&mut self, dims: (f64, f64));
+	/// Resizes the target surface to `dims` in device pixels, regardless of the scale factor.
+	fn resize_surface(&mut self, dims: (f64, f64)) -> Result<(), Self::Error>;
+
+	/// Scales all drawing by `scale`, so that coordinates are in logical pixels on a display with that scale factor. This
+	/// replaces any transformation of the current frame.
+	fn set_scale_factor(&mut self, scale: f64);
+
+	/// Moves the origin of user space by (tx, ty) in the current user-space units.
+	fn translate(&mut self, tx: f64, ty: f64);
+
+	/// Scales user space by `sx` horizontally and `sy` vertically.
+	fn scale(&mut self, sx: f64, sy: f64);
+
+	/// Rotates user space by `angle` radians. Positive angles rotate from the positive x axis towards the positive y axis.
+	fn rotate(&mut self, angle: f64);
+
+	/// Applies the affine transformation `[xx, yx, xy, yy, x0, y0]` on top of the current one, where a point (x, y) maps to
+	/// (xx * x + xy * y + x0, yx * x + yy * y + y0).
+	fn transform(&mut self, matrix: [f64; 6]);
+
+	/// Undoes all transformations, leaving only the scaling by the scale factor, so that coordinates are logical pixels
+	/// again.
+	fn set_identity_matrix(&mut self);
+
+	/// Pushes a copy of the current drawing state (source, line width, transformation, clip, font) onto a stack. Calls nest,
+	/// and each must be balanced by a later `restore`.
+	fn save(&mut self);
+
+	/// Pops the state pushed by the matching `save`, undoing any state changes made since then. The current path is not
+	/// part of the state and is left alone.
+	fn restore(&mut self);
 
 	fn move_to(&mut self, x: f64, y: f64);
 
 	fn line_to(&mut self, x: f64, y: f64);
 
+	/// Adds a cubic Bézier curve from the current point to (x3, y3), using (x1, y1) and (x2, y2) as the control points.
+	fn curve_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, x3: f64, y3: f64);
+
+	/// Like `line_to`, but with the end point given as an offset from the current point.
+	fn rel_line_to(&mut self, dx: f64, dy: f64);
+
+	/// Like `curve_to`, but with all points given as offsets from the current point.
+	fn rel_curve_to(&mut self, dx1: f64, dy1: f64, dx2: f64, dy2: f64, dx3: f64, dy3: f64);
+
 	fn set_line_width(&mut self, width: f64);
 
+	/// Makes strokes dashed, alternating between "on" and "off" lengths from `dashes`, in user-space units. `offset` is how
+	/// far into the pattern each stroke starts. An empty slice makes strokes solid again.
+	fn set_dash(&mut self, dashes: &[f64], offset: f64);
+
+	fn set_line_cap(&mut self, cap: LineCap);
+
+	fn set_line_join(&mut self, join: LineJoin);
+
 	fn set_source_rgba(&mut self, r: f64, g: f64, b: f64, a: f64);
 
+	/// Sets the source to a linear gradient from (x0, y0) to (x1, y1). `stops` are (offset, r, g, b, a). The gradient stays
+	/// the source until the next `set_source_*` call.
+	fn set_source_linear_gradient(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, stops: &[(f64, f64, f64, f64, f64)]);
+
+	/// Sets the source to a radial gradient between the circle at (cx0, cy0) with radius `r0` and the one at (cx1, cy1)
+	/// with radius `r1`. `stops` are (offset, r, g, b, a). The gradient stays the source until the next `set_source_*` call.
+	fn set_source_radial_gradient(
+		&mut self,
+		cx0: f64,
+		cy0: f64,
+		r0: f64,
+		cx1: f64,
+		cy1: f64,
+		r1: f64,
+		stops: &[(f64, f64, f64, f64, f64)],
+	);
+
+	/// Sets the source to `gradient`.
+	fn set_source_gradient(&mut self, gradient: &Gradient) {
+		match *gradient {
+			Gradient::Linear { start, end, ref stops } => self.set_source_linear_gradient(start.0, start.1, end.0, end.1, stops),
+			Gradient::Radial {
+				inner_center,
+				inner_radius,
+				outer_center,
+				outer_radius,
+				ref stops,
+			} => self.set_source_radial_gradient(
+				inner_center.0,
+				inner_center.1,
+				inner_radius,
+				outer_center.0,
+				outer_center.1,
+				outer_radius,
+				stops,
+			),
+		}
+	}
+
+	/// Sets the maximum error, in device pixels, allowed when approximating curves with line segments. Smaller values give
+	/// more precise curves at the cost of speed.
+	fn set_tolerance(&mut self, tolerance: f64);
+
+	/// Selects the font used for text by family name, such as "sans-serif" or "DejaVu Sans". Falls back to a similar font
+	/// if the family isn't available.
+	fn set_font_face(&mut self, family: &str, slant: FontSlant, weight: FontWeight);
+
+	/// Sets the font size in user-space units.
+	fn set_font_size(&mut self, size: f64);
+
 	fn get_font_extents(&self) -> FontExtents;
 
 	fn get_text_extents(&self, text: &str) -> TextExtents;
 
 	fn draw_text(&mut self, text: &str);
 
+	/// Enables sub-pixel antialiasing of text for a display with the given sub-pixel order, or disables it with
+	/// `SubpixelOrder::None`. This only looks right on opaque backgrounds; on translucent surfaces, like the 32-bit XCB
+	/// window, it produces color fringes and should be left off.
+	fn set_subpixel_order(&mut self, order: SubpixelOrder);
+
+	/// Gets the bounding box of the current path in user-space coordinates.
+	fn path_extents(&self) -> Rect;
+
+	/// Gets the rectangle that `text` would cover if drawn at the current point.
+	fn text_bounds(&self, text: &str) -> Rect;
+
+	/// Shortens `text` to the longest prefix that fits in `max_width` with an ellipsis appended. Returns the text unchanged
+	/// if it already fits, or an empty string if not even the ellipsis fits.
+	fn ellipsize(&self, text: &str, max_width: f64) -> String {
+		const ELLIPSIS: &str = "\u{2026}";
+
+		if self.get_text_extents(text).x_advance <= max_width {
+			return text.to_owned();
+		}
+		if self.get_text_extents(ELLIPSIS).x_advance > max_width {
+			return String::new();
+		}
+
+		// Binary search over the char boundaries for the longest prefix that fits
+		let boundaries = text.char_indices().map(|(i, _)| i).collect::<Vec<_>>();
+		let prefix = |chars: usize| &text[..boundaries.get(chars).cloned().unwrap_or(text.len())];
+		let (mut low, mut high) = (0, boundaries.len());
+		while low < high {
+			let mid = (low + high + 1) / 2;
+			let candidate = format!("{}{}", prefix(mid), ELLIPSIS);
+			if self.get_text_extents(&candidate).x_advance <= max_width {
+				low = mid;
+			} else {
+				high = mid - 1;
+			}
+		}
+		format!("{}{}", prefix(low), ELLIPSIS)
+	}
+
 	fn new_path(&mut self);
 
 	fn new_sub_path(&mut self);
@@ -56,11 +317,89 @@ pub trait DrawingBackend: Sized + 'static {
 
 	fn stroke(&mut self);
 
+	/// Strokes a line between two points in user space with a width in device pixels, snapping the endpoints to the device
+	/// pixel grid so the line stays crisp at any scale. This replaces the current path.
+	fn device_aligned_line(&mut self, from: (f64, f64), to: (f64, f64), device_width: f64);
+
 	fn fill(&mut self);
 
+	/// Fills many rectangles at once. Consecutive rectangles of the same color are filled as a single path, so sorting the
+	/// slice by color gives the fewest fills. Rectangles are painted in slice order, so later rectangles are drawn over
+	/// earlier ones. This replaces the current path, but the current source is left alone.
+	fn fill_rects(&mut self, rects: &[(Rect, Color)]) {
+		self.save();
+		let mut start = 0;
+		while start < rects.len() {
+			let color = rects[start].1;
+			let end = rects[start..]
+				.iter()
+				.position(|(_, run_color)| *run_color != color)
+				.map(|len| start + len)
+				.unwrap_or_else(|| rects.len());
+			self.new_path();
+			for (rect, _) in &rects[start..end] {
+				self.rect(rect.x, rect.y, rect.width, rect.height);
+			}
+			self.set_source_rgba(color.r, color.g, color.b, color.a);
+			self.fill();
+			start = end;
+		}
+		self.restore();
+	}
+
+	/// Intersects the clip region with the current path and clears the path. Later drawing only affects the area inside the
+	/// clip. The clip is part of the state saved by `save`, so a clip set between `save` and `restore` only lasts until the
+	/// `restore`.
+	fn clip(&mut self);
+
+	/// Removes all clipping, including clips set before the last `save`.
+	fn reset_clip(&mut self);
+
+	/// Intersects the clip region with a rectangle. This replaces the current path.
+	fn clip_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+		self.new_path();
+		self.rect(x, y, width, height);
+		self.clip();
+	}
+
 	fn paint(&mut self);
 
+	/// Paints the current source through a linear alpha gradient between `start` and `end`. `stops` are (offset, alpha)
+	/// pairs, with offsets from 0.0 at `start` to 1.0 at `end`.
+	fn mask_linear_gradient(&mut self, start: (f64, f64), end: (f64, f64), stops: &[(f64, f64)]);
+
+	/// Draws `image` at its natural size with its top left corner at (x, y), blending it over what's already there. The
+	/// current source and path are left alone.
+	fn draw_image(&mut self, image: &ImageData, x: f64, y: f64);
+
+	/// Paints the current source through the alpha channel of `image`, placed with its top left corner at (x, y).
+	fn mask_image(&mut self, image: &ImageData, x: f64, y: f64);
+
+	/// Replaces everything inside the clip with the current source, including its alpha, instead of blending over it. Set
+	/// the source first, or use `clear_color`.
 	fn clear(&mut self);
 
+	/// Replaces everything inside the clip with the given color. The current source is left alone.
+	fn clear_color(&mut self, r: f64, g: f64, b: f64, a: f64) {
+		self.save();
+		self.set_source_rgba(r, g, b, a);
+		self.clear();
+		self.restore();
+	}
+
 	fn present(&mut self);
+
+	/// Enables or disables damage tracking. While enabled, the device-space bounding boxes of drawing operations are
+	/// recorded, and presenting only updates those areas of the target instead of replacing the whole frame, which keeps
+	/// small updates like a changing label cheap. Areas that weren't drawn to keep their previous contents.
+	fn set_damage_tracking(&mut self, enabled: bool);
+
+	/// The areas drawn to since the last present, in device coordinates. Always empty when damage tracking is disabled.
+	fn damage(&self) -> &[Rect];
+
+	/// Presents the current frame and blocks until all drawing has actually reached the target, so that a readback or
+	/// screenshot taken immediately afterwards reflects the final frame.
+	fn present_and_sync(&mut self) {
+		self.present();
+	}
 }
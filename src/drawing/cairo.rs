@@ -1,15 +1,29 @@
 use crate::drawing::{DrawingBackend, SurfaceCreator};
+use crate::window::WindowBackend;
 
+use crate::drawing::AlphaMode;
 use crate::drawing::FontExtents;
+use crate::drawing::FontSlant;
+use crate::drawing::FontWeight;
+use crate::drawing::ImageData;
+use crate::drawing::LineCap;
+use crate::drawing::LineJoin;
+use crate::drawing::PathElement;
+use crate::drawing::Rect;
+use crate::drawing::SubpixelOrder;
 use crate::drawing::TextExtents;
 use cairo::Context;
-use cairo::FontSlant;
-use cairo::FontWeight;
 use cairo::Surface;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::path::Path;
 
 pub struct CairoBackend {
 	pub ctx: Context,
 	pub surface: <Self as DrawingBackend>::Surface,
+	damage: Option<Vec<Rect>>,
+	scale: f64,
 }
 
 impl From<cairo::TextExtents> for TextExtents {
@@ -37,37 +51,436 @@ impl From<cairo::FontExtents> for FontExtents {
 	}
 }
 
+impl From<LineCap> for cairo::LineCap {
+	fn from(cap: LineCap) -> Self {
+		match cap {
+			LineCap::Butt => cairo::LineCap::Butt,
+			LineCap::Round => cairo::LineCap::Round,
+			LineCap::Square => cairo::LineCap::Square,
+		}
+	}
+}
+
+impl From<LineJoin> for cairo::LineJoin {
+	fn from(join: LineJoin) -> Self {
+		match join {
+			LineJoin::Miter => cairo::LineJoin::Miter,
+			LineJoin::Round => cairo::LineJoin::Round,
+			LineJoin::Bevel => cairo::LineJoin::Bevel,
+		}
+	}
+}
+
+impl From<FontSlant> for cairo::FontSlant {
+	fn from(slant: FontSlant) -> Self {
+		match slant {
+			FontSlant::Normal => cairo::FontSlant::Normal,
+			FontSlant::Italic => cairo::FontSlant::Italic,
+			FontSlant::Oblique => cairo::FontSlant::Oblique,
+		}
+	}
+}
+
+impl From<FontWeight> for cairo::FontWeight {
+	fn from(weight: FontWeight) -> Self {
+		match weight {
+			FontWeight::Normal => cairo::FontWeight::Normal,
+			FontWeight::Bold => cairo::FontWeight::Bold,
+		}
+	}
+}
+
+impl From<cairo::PathSegment> for PathElement {
+	fn from(t: cairo::PathSegment) -> Self {
+		match t {
+			cairo::PathSegment::MoveTo(p) => PathElement::MoveTo(p),
+			cairo::PathSegment::LineTo(p) => PathElement::LineTo(p),
+			cairo::PathSegment::CurveTo(p1, p2, p3) => PathElement::CurveTo(p1, p2, p3),
+			cairo::PathSegment::ClosePath => PathElement::ClosePath,
+		}
+	}
+}
+
 pub struct CairoSurface(Surface);
 
 impl CairoSurface {
 	pub fn from_surface(surface: Surface) -> Self {
 		CairoSurface(surface)
 	}
+
+	/// Creates an unbounded recording surface that captures drawing operations without rasterizing them, so they can
+	/// later be replayed onto another surface with `CairoBackend::replay_to`.
+	pub fn recording() -> Result<Self, CairoBackendError> {
+		let surface = cairo::RecordingSurface::create(cairo::Content::ColorAlpha, None)
+			.ok_or(CairoBackendError::SurfaceCreation(cairo::Status::NoMemory))?;
+		CairoSurface::checked((*surface).clone())
+	}
+
+	/// Creates an image surface from tightly packed 8-bit RGBA pixel data, converting it to the premultiplied native-endian
+	/// ARGB layout Cairo expects.
+	pub fn load_image(rgba: &[u8], width: u32, height: u32, alpha_mode: AlphaMode) -> Result<Self, CairoBackendError> {
+		let stride = cairo::Format::ARgb32
+			.stride_for_width(width)
+			.map_err(|_| CairoBackendError::SurfaceCreation(cairo::Status::InvalidStride))?;
+		let data = rgba_to_argb32(rgba, width, height, stride as usize, alpha_mode);
+		let surface = cairo::ImageSurface::create_for_data(data, cairo::Format::ARgb32, width as i32, height as i32, stride)
+			.map_err(CairoBackendError::SurfaceCreation)?;
+		Ok(CairoSurface((*surface).clone()))
+	}
+
+	/// Creates an in-memory ARGB32 image surface, for rendering offscreen without a window.
+	pub fn image(width: u32, height: u32) -> Result<Self, CairoBackendError> {
+		let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width as i32, height as i32)
+			.map_err(CairoBackendError::SurfaceCreation)?;
+		Ok(CairoSurface((*surface).clone()))
+	}
+
+	/// Reads the pixels of an ARGB32 image surface back as tightly packed 8-bit RGBA, with the color channels premultiplied
+	/// by alpha. Returns `None` for any other kind of surface.
+	pub fn read_rgba(&self) -> Option<Vec<u8>> {
+		unsafe {
+			let raw = self.0.to_raw_none();
+			if self.0.get_type() != cairo::SurfaceType::Image
+				|| cairo::Format::from(cairo_sys::cairo_image_surface_get_format(raw)) != cairo::Format::ARgb32
+			{
+				return None;
+			}
+			self.0.flush();
+			let width = cairo_sys::cairo_image_surface_get_width(raw) as u32;
+			let height = cairo_sys::cairo_image_surface_get_height(raw) as u32;
+			let stride = cairo_sys::cairo_image_surface_get_stride(raw) as usize;
+			let data = cairo_sys::cairo_image_surface_get_data(raw);
+			if data.is_null() {
+				return None;
+			}
+			let data = std::slice::from_raw_parts(data, stride * height as usize);
+			Some(argb32_to_rgba(data, width, height, stride))
+		}
+	}
+
+	/// Creates a surface that renders to an SVG document at `path`, with dimensions given in points.
+	pub fn svg(path: &Path, width: f64, height: f64) -> Result<Self, CairoBackendError> {
+		let surface = cairo::SvgSurface::new(width, height, path);
+		CairoSurface::checked((*surface).clone())
+	}
+
+	/// Creates a surface that renders to a PDF document at `path`, with dimensions given in points. Use
+	/// `CairoBackend::show_page` to start a new page.
+	pub fn pdf(path: &Path, width: f64, height: f64) -> Result<Self, CairoBackendError> {
+		let surface = cairo::PdfSurface::new(width, height, path);
+		CairoSurface::checked((*surface).clone())
+	}
+
+	/// Wraps a newly created surface, failing if Cairo put it in an error state instead of reporting the error, as it
+	/// does when the file behind a document surface can't be written.
+	fn checked(surface: Surface) -> Result<Self, CairoBackendError> {
+		match surface.status() {
+			cairo::Status::Success => Ok(CairoSurface(surface)),
+			status => Err(CairoBackendError::SurfaceCreation(status)),
+		}
+	}
+
+	/// Gets the underlying Cairo surface, e.g. to use it as a source when compositing onto another surface. Cloning it
+	/// takes a new reference to the same surface rather than copying its contents.
+	pub fn as_cairo_surface(&self) -> &Surface {
+		&self.0
+	}
+
+	/// Gets a raw pointer to the underlying Cairo surface without taking a reference to it. The pointer is only valid as
+	/// long as this `CairoSurface` is alive, and the caller must not draw to it while a backend is drawing to it.
+	pub fn as_ptr(&self) -> *mut cairo_sys::cairo_surface_t {
+		self.0.to_raw_none()
+	}
+}
+
+pub(crate) fn rgba_to_argb32(rgba: &[u8], width: u32, height: u32, stride: usize, alpha_mode: AlphaMode) -> Vec<u8> {
+	fn premultiply(channel: u8, alpha: u8) -> u8 {
+		((channel as u32 * alpha as u32 + 127) / 255) as u8
+	}
+
+	let mut data = vec![0u8; stride * height as usize];
+	for (y, row) in rgba.chunks(width as usize * 4).take(height as usize).enumerate() {
+		for (x, pixel) in row.chunks_exact(4).enumerate() {
+			let (r, g, b, a) = (pixel[0], pixel[1], pixel[2], pixel[3]);
+			let (r, g, b) = match alpha_mode {
+				AlphaMode::Straight => (premultiply(r, a), premultiply(g, a), premultiply(b, a)),
+				AlphaMode::Premultiplied => (r, g, b),
+			};
+			let argb = (a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32;
+			let offset = y * stride + x * 4;
+			data[offset..offset + 4].copy_from_slice(&argb.to_ne_bytes());
+		}
+	}
+	data
+}
+
+/// Converts native-endian premultiplied ARGB32 pixel data to tightly packed RGBA, keeping the alpha premultiplied.
+pub(crate) fn argb32_to_rgba(data: &[u8], width: u32, height: u32, stride: usize) -> Vec<u8> {
+	let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+	for row in data.chunks(stride).take(height as usize) {
+		for pixel in row[..width as usize * 4].chunks_exact(4) {
+			let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+			rgba.extend_from_slice(&[(argb >> 16) as u8, (argb >> 8) as u8, argb as u8, (argb >> 24) as u8]);
+		}
+	}
+	rgba
+}
+
+/// Loads a PNG file into premultiplied RGBA pixels, ready to be drawn with `DrawingBackend::draw_image`.
+pub fn load_png(path: &Path) -> io::Result<ImageData> {
+	fn to_io_error<E: std::fmt::Debug>(err: E) -> io::Error {
+		io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err))
+	}
+
+	let png = cairo::ImageSurface::create_from_png(&mut File::open(path)?).map_err(|err| match err {
+		cairo::IoError::Io(err) => err,
+		cairo::IoError::Cairo(status) => to_io_error(status),
+	})?;
+	let (width, height) = (png.get_width(), png.get_height());
+
+	// Cairo may decode to several formats depending on the file, so normalize to ARGB32 by painting onto a new surface
+	let mut image = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height).map_err(to_io_error)?;
+	{
+		let ctx = Context::new(&image);
+		ctx.set_source_surface(&png, 0.0, 0.0);
+		ctx.set_operator(cairo::Operator::Source);
+		ctx.paint();
+	}
+	let stride = image.get_stride() as usize;
+	let data = image.get_data().map_err(to_io_error)?;
+	Ok(ImageData {
+		pixels: argb32_to_rgba(&data, width as u32, height as u32, stride),
+		width: width as u32,
+		height: height as u32,
+		alpha_mode: AlphaMode::Premultiplied,
+	})
+}
+
+/// Creates fixed-size in-memory image surfaces for any window backend, ignoring the window. Useful for rendering offscreen,
+/// e.g. for thumbnails or for comparing rendered output in tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageSurfaceCreator {
+	pub width: u32,
+	pub height: u32,
+}
+
+impl<W: WindowBackend> SurfaceCreator<W, CairoBackend> for ImageSurfaceCreator {
+	type Error = CairoBackendError;
+
+	fn create_surface(&self, _window: &W::Window) -> Result<CairoSurface, CairoBackendError> {
+		CairoSurface::image(self.width, self.height)
+	}
+}
+
+#[test]
+fn image_surface_reads_back_presented_pixels() {
+	let mut cairo = CairoBackend::new(CairoSurface::image(2, 1).unwrap());
+	cairo.rect(0.0, 0.0, 1.0, 1.0);
+	cairo.set_source_rgba(1.0, 0.0, 0.0, 1.0);
+	cairo.fill();
+	cairo.present();
+	assert_eq!(cairo.surface().read_rgba().unwrap(), [255, 0, 0, 255, 0, 0, 0, 0]);
+}
+
+#[test]
+fn argb32_round_trips_through_rgba() {
+	let rgba = [10, 20, 30, 40, 50, 60, 70, 80];
+	let data = rgba_to_argb32(&rgba, 1, 2, 8, AlphaMode::Premultiplied);
+	assert_eq!(argb32_to_rgba(&data, 1, 2, 8), rgba);
+}
+
+#[test]
+fn straight_alpha_is_premultiplied() {
+	// Half-transparent red, which Cairo stores with the red channel scaled down by alpha
+	let surface = CairoSurface::load_image(&[255, 0, 0, 128], 1, 1, AlphaMode::Straight).unwrap();
+	let pixel = unsafe {
+		let data = cairo_sys::cairo_image_surface_get_data(surface.as_ptr());
+		u32::from_ne_bytes(*(data as *const [u8; 4]))
+	};
+	assert_eq!(pixel, 0x8080_0000);
+	assert_eq!(surface.read_rgba().unwrap(), [128, 0, 0, 128]);
+
+	let surface = CairoSurface::load_image(&[128, 0, 0, 128], 1, 1, AlphaMode::Premultiplied).unwrap();
+	assert_eq!(surface.read_rgba().unwrap(), [128, 0, 0, 128]);
+}
+
+impl CairoBackend {
+	/// Creates a backend that draws onto a Cairo surface owned by someone else, such as a host application embedding the
+	/// drawing.
+	///
+	/// # Safety
+	///
+	/// `ptr` must point to a valid Cairo surface. The backend takes its own reference to the surface, so the caller may
+	/// release theirs, but nothing else should draw to the surface while the backend is drawing to it.
+	pub unsafe fn from_raw_surface(ptr: *mut cairo_sys::cairo_surface_t) -> Self {
+		CairoBackend::new(CairoSurface::from_surface(Surface::from_raw_none(ptr)))
+	}
+
+	/// Gets the surface this backend draws to, for reading from it or compositing it elsewhere. Pending drawing operations
+	/// are flushed first, but anything drawn after this call may not be visible on the surface until the next call or the
+	/// next present.
+	pub fn surface(&self) -> &CairoSurface {
+		self.surface.0.flush();
+		&self.surface
+	}
+
+	/// Replays everything presented to this backend's surface onto `target`. This is mostly useful with a recording
+	/// surface, where the operations are replayed as vectors instead of as a rasterized image.
+	pub fn replay_to(&self, target: &CairoSurface) {
+		let ctx = Context::new(&target.0);
+		ctx.set_source_surface(&self.surface.0, 0.0, 0.0);
+		ctx.paint();
+		target.0.flush();
+	}
+
+	/// Copies the current path, which is useful for inspecting the geometry that was built when debugging or testing.
+	pub fn copy_path(&self) -> Vec<PathElement> {
+		self.ctx.copy_path().iter().map(PathElement::from).collect()
+	}
+
+	/// Like `copy_path`, but with curves flattened into line segments according to the current tolerance.
+	pub fn copy_path_flat(&self) -> Vec<PathElement> {
+		self.ctx.copy_path_flat().iter().map(PathElement::from).collect()
+	}
+
+	/// Records the user-space box from (x1, y1) to (x2, y2) as damaged if damage tracking is enabled.
+	fn add_damage(&mut self, (x1, y1, x2, y2): (f64, f64, f64, f64)) {
+		let damage = match self.damage {
+			Some(ref mut damage) => damage,
+			None => return,
+		};
+		if x2 <= x1 || y2 <= y1 {
+			return;
+		}
+		let corners = [
+			self.ctx.user_to_device(x1, y1),
+			self.ctx.user_to_device(x2, y1),
+			self.ctx.user_to_device(x1, y2),
+			self.ctx.user_to_device(x2, y2),
+		];
+		let min_x = corners.iter().map(|c| c.0).fold(std::f64::INFINITY, f64::min).floor();
+		let min_y = corners.iter().map(|c| c.1).fold(std::f64::INFINITY, f64::min).floor();
+		let max_x = corners.iter().map(|c| c.0).fold(std::f64::NEG_INFINITY, f64::max).ceil();
+		let max_y = corners.iter().map(|c| c.1).fold(std::f64::NEG_INFINITY, f64::max).ceil();
+		damage.push(Rect {
+			x: min_x,
+			y: min_y,
+			width: max_x - min_x,
+			height: max_y - min_y,
+		});
+	}
+
+	/// Writes the contents of the surface to a PNG file at `path`. Like `surface`, this doesn't include anything drawn since
+	/// the last present. Surfaces that aren't image surfaces, like windows, are copied into a temporary image first.
+	pub fn write_to_png(&self, path: &Path) -> io::Result<()> {
+		fn to_io_error<E: std::fmt::Debug>(err: E) -> io::Error {
+			io::Error::new(io::ErrorKind::Other, format!("{:?}", err))
+		}
+
+		let surface = &self.surface().0;
+		let image = match cairo::ImageSurface::try_from(surface.clone()) {
+			Ok(image) => image,
+			Err(_) => {
+				// The clip of a fresh context covers the whole surface
+				let (x1, y1, x2, y2) = Context::new(surface).clip_extents();
+				let image =
+					cairo::ImageSurface::create(cairo::Format::ARgb32, (x2 - x1) as i32, (y2 - y1) as i32).map_err(to_io_error)?;
+				let ctx = Context::new(&image);
+				ctx.set_source_surface(surface, -x1, -y1);
+				ctx.set_operator(cairo::Operator::Source);
+				ctx.paint();
+				image
+			}
+		};
+		image.write_to_png(&mut File::create(path)?).map_err(|err| match err {
+			cairo::IoError::Io(err) => err,
+			cairo::IoError::Cairo(status) => to_io_error(status),
+		})
+	}
+
+	/// Presents the current frame and emits it as a page of a paginated surface (PDF), starting a new blank page.
+	pub fn show_page(&mut self) {
+		self.present();
+		// Shows the page of the context's original target, not of the group pushed by present
+		self.ctx.show_page();
+	}
 }
 
 impl DrawingBackend for CairoBackend {
 	type Surface = CairoSurface;
+	type Error = CairoBackendError;
 
 	fn new(surface: Self::Surface) -> Self {
 		let mut surface = surface;
 		let mut cairo = CairoBackend {
 			ctx: Context::new(&surface.0),
 			surface,
+			damage: None,
+			scale: 1.0,
 		};
-		cairo
-			.ctx
-			.select_font_face(".SF Compact Display", FontSlant::Normal, FontWeight::Normal);
-		cairo.ctx.set_font_size(13.5);
+		cairo.set_font_face("sans-serif", FontSlant::Normal, FontWeight::Normal);
+		cairo.set_font_size(13.5);
 		cairo.ctx.push_group();
 		cairo
 	}
 
-	fn resize_surface(&mut self, dims: (f64, f64)) {
+	fn resize_surface(&mut self, dims: (f64, f64)) -> Result<(), Self::Error> {
 		// TODO: make cross platform
-		log::warn!("Resized surface which only works on Xlib as of now");
-		unsafe {
-			cairo_sys::cairo_xlib_surface_set_size(self.surface.0.to_raw_none(), dims.0 as i32, dims.1 as i32);
+		match self.surface.0.get_type() {
+			cairo::SurfaceType::Xlib => unsafe {
+				cairo_sys::cairo_xlib_surface_set_size(self.surface.0.to_raw_none(), dims.0 as i32, dims.1 as i32);
+			},
+			cairo::SurfaceType::Xcb => unsafe {
+				cairo_sys::cairo_xcb_surface_set_size(self.surface.0.to_raw_none(), dims.0 as i32, dims.1 as i32);
+			},
+			// Image surfaces have a fixed size, but window backends that draw through one (like Wayland) allocate it
+			// larger than the window, so anything that still fits is fine
+			cairo::SurfaceType::Image => unsafe {
+				let raw = self.surface.0.to_raw_none();
+				if dims.0 > cairo_sys::cairo_image_surface_get_width(raw) as f64
+					|| dims.1 > cairo_sys::cairo_image_surface_get_height(raw) as f64
+				{
+					return Err(CairoBackendError::Unsupported("resize_surface"));
+				}
+			},
+			_ => return Err(CairoBackendError::Unsupported("resize_surface")),
 		}
+		Ok(())
+	}
+
+	fn set_scale_factor(&mut self, scale: f64) {
+		self.scale = scale;
+		self.ctx.identity_matrix();
+		self.ctx.scale(scale, scale);
+	}
+
+	fn translate(&mut self, tx: f64, ty: f64) {
+		self.ctx.translate(tx, ty);
+	}
+
+	fn scale(&mut self, sx: f64, sy: f64) {
+		self.ctx.scale(sx, sy);
+	}
+
+	fn rotate(&mut self, angle: f64) {
+		self.ctx.rotate(angle);
+	}
+
+	fn transform(&mut self, [xx, yx, xy, yy, x0, y0]: [f64; 6]) {
+		self.ctx.transform(cairo::Matrix::new(xx, yx, xy, yy, x0, y0));
+	}
+
+	fn set_identity_matrix(&mut self) {
+		self.ctx.identity_matrix();
+		self.ctx.scale(self.scale, self.scale);
+	}
+
+	fn save(&mut self) {
+		self.ctx.save();
+	}
+
+	fn restore(&mut self) {
+		self.ctx.restore();
 	}
 
 	fn move_to(&mut self, x: f64, y: f64) {
@@ -78,14 +491,75 @@ impl DrawingBackend for CairoBackend {
 		self.ctx.line_to(x, y);
 	}
 
+	fn curve_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, x3: f64, y3: f64) {
+		self.ctx.curve_to(x1, y1, x2, y2, x3, y3);
+	}
+
+	fn rel_line_to(&mut self, dx: f64, dy: f64) {
+		self.ctx.rel_line_to(dx, dy);
+	}
+
+	fn rel_curve_to(&mut self, dx1: f64, dy1: f64, dx2: f64, dy2: f64, dx3: f64, dy3: f64) {
+		self.ctx.rel_curve_to(dx1, dy1, dx2, dy2, dx3, dy3);
+	}
+
 	fn set_line_width(&mut self, width: f64) {
 		self.ctx.set_line_width(width);
 	}
 
+	fn set_dash(&mut self, dashes: &[f64], offset: f64) {
+		self.ctx.set_dash(dashes, offset);
+	}
+
+	fn set_line_cap(&mut self, cap: LineCap) {
+		self.ctx.set_line_cap(cap.into());
+	}
+
+	fn set_line_join(&mut self, join: LineJoin) {
+		self.ctx.set_line_join(join.into());
+	}
+
 	fn set_source_rgba(&mut self, r: f64, g: f64, b: f64, a: f64) {
 		self.ctx.set_source_rgba(r, g, b, a);
 	}
 
+	fn set_source_linear_gradient(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, stops: &[(f64, f64, f64, f64, f64)]) {
+		let gradient = cairo::LinearGradient::new(x0, y0, x1, y1);
+		for &(offset, r, g, b, a) in stops {
+			gradient.add_color_stop_rgba(offset, r, g, b, a);
+		}
+		self.ctx.set_source(&gradient);
+	}
+
+	fn set_source_radial_gradient(
+		&mut self,
+		cx0: f64,
+		cy0: f64,
+		r0: f64,
+		cx1: f64,
+		cy1: f64,
+		r1: f64,
+		stops: &[(f64, f64, f64, f64, f64)],
+	) {
+		let gradient = cairo::RadialGradient::new(cx0, cy0, r0, cx1, cy1, r1);
+		for &(offset, r, g, b, a) in stops {
+			gradient.add_color_stop_rgba(offset, r, g, b, a);
+		}
+		self.ctx.set_source(&gradient);
+	}
+
+	fn set_tolerance(&mut self, tolerance: f64) {
+		self.ctx.set_tolerance(tolerance);
+	}
+
+	fn set_font_face(&mut self, family: &str, slant: FontSlant, weight: FontWeight) {
+		self.ctx.select_font_face(family, slant.into(), weight.into());
+	}
+
+	fn set_font_size(&mut self, size: f64) {
+		self.ctx.set_font_size(size);
+	}
+
 	fn get_font_extents(&self) -> FontExtents {
 		self.ctx.font_extents().into()
 	}
@@ -96,9 +570,48 @@ impl DrawingBackend for CairoBackend {
 	}
 
 	fn draw_text(&mut self, text: &str) {
+		if self.damage.is_some() {
+			let bounds = self.text_bounds(text);
+			self.add_damage((bounds.x, bounds.y, bounds.x + bounds.width, bounds.y + bounds.height));
+		}
 		self.ctx.show_text(text);
 	}
 
+	fn set_subpixel_order(&mut self, order: SubpixelOrder) {
+		let mut options = cairo::FontOptions::new();
+		let (subpixel_order, antialias) = match order {
+			SubpixelOrder::Rgb => (cairo::SubpixelOrder::Rgb, cairo::Antialias::Subpixel),
+			SubpixelOrder::Bgr => (cairo::SubpixelOrder::Bgr, cairo::Antialias::Subpixel),
+			SubpixelOrder::Vrgb => (cairo::SubpixelOrder::Vrgb, cairo::Antialias::Subpixel),
+			SubpixelOrder::Vbgr => (cairo::SubpixelOrder::Vbgr, cairo::Antialias::Subpixel),
+			SubpixelOrder::None => (cairo::SubpixelOrder::Default, cairo::Antialias::Gray),
+		};
+		options.set_subpixel_order(subpixel_order);
+		options.set_antialias(antialias);
+		self.ctx.set_font_options(&options);
+	}
+
+	fn path_extents(&self) -> Rect {
+		let (x1, y1, x2, y2) = self.ctx.path_extents();
+		Rect {
+			x: x1,
+			y: y1,
+			width: x2 - x1,
+			height: y2 - y1,
+		}
+	}
+
+	fn text_bounds(&self, text: &str) -> Rect {
+		let (x, y) = self.ctx.get_current_point();
+		let extents = self.get_text_extents(text);
+		Rect {
+			x: x + extents.x_bearing,
+			y: y + extents.y_bearing,
+			width: extents.width,
+			height: extents.height,
+		}
+	}
+
 	fn new_path(&mut self) {
 		self.ctx.new_path();
 	}
@@ -116,18 +629,85 @@ impl DrawingBackend for CairoBackend {
 	}
 
 	fn stroke(&mut self) {
+		self.add_damage(self.ctx.stroke_extents());
+		self.ctx.stroke();
+	}
+
+	fn device_aligned_line(&mut self, from: (f64, f64), to: (f64, f64), device_width: f64) {
+		// Lines with an odd pixel width cover whole pixels when centered on a pixel center, even widths when centered on a
+		// pixel edge
+		let odd_width = device_width.round() as i64 % 2 == 1;
+		let snap = |v: f64| if odd_width { v.floor() + 0.5 } else { v.round() };
+
+		let from = self.ctx.user_to_device(from.0, from.1);
+		let to = self.ctx.user_to_device(to.0, to.1);
+		self.ctx.save();
+		self.ctx.identity_matrix();
+		self.ctx.new_path();
+		self.ctx.move_to(snap(from.0), snap(from.1));
+		self.ctx.line_to(snap(to.0), snap(to.1));
+		self.ctx.set_line_width(device_width);
+		self.add_damage(self.ctx.stroke_extents());
 		self.ctx.stroke();
+		self.ctx.restore();
 	}
 
 	fn fill(&mut self) {
+		self.add_damage(self.ctx.fill_extents());
 		self.ctx.fill();
 	}
 
+	fn clip(&mut self) {
+		self.ctx.clip();
+	}
+
+	fn reset_clip(&mut self) {
+		self.ctx.reset_clip();
+	}
+
 	fn paint(&mut self) {
+		self.add_damage(self.ctx.clip_extents());
 		self.ctx.paint();
 	}
 
+	fn mask_linear_gradient(&mut self, start: (f64, f64), end: (f64, f64), stops: &[(f64, f64)]) {
+		let gradient = cairo::LinearGradient::new(start.0, start.1, end.0, end.1);
+		for &(offset, alpha) in stops {
+			gradient.add_color_stop_rgba(offset, 0.0, 0.0, 0.0, alpha);
+		}
+		self.add_damage(self.ctx.clip_extents());
+		self.ctx.mask(&gradient);
+	}
+
+	fn draw_image(&mut self, image: &ImageData, x: f64, y: f64) {
+		let surface = match CairoSurface::load_image(&image.pixels, image.width, image.height, image.alpha_mode) {
+			Ok(surface) => surface,
+			Err(e) => {
+				log::error!("Failed to load image: {}", e);
+				return;
+			}
+		};
+		self.add_damage((x, y, x + image.width as f64, y + image.height as f64));
+		self.ctx.save();
+		self.ctx.set_source_surface(&surface.0, x, y);
+		self.ctx.paint();
+		self.ctx.restore();
+	}
+
+	fn mask_image(&mut self, image: &ImageData, x: f64, y: f64) {
+		let surface = match CairoSurface::load_image(&image.pixels, image.width, image.height, image.alpha_mode) {
+			Ok(surface) => surface,
+			Err(e) => {
+				log::error!("Failed to load mask image: {}", e);
+				return;
+			}
+		};
+		self.add_damage((x, y, x + image.width as f64, y + image.height as f64));
+		self.ctx.mask_surface(&surface.0, x, y);
+	}
+
 	fn clear(&mut self) {
+		self.add_damage(self.ctx.clip_extents());
 		let old_operator = self.ctx.get_operator();
 		self.ctx.set_operator(cairo::Operator::Source);
 		self.ctx.paint();
@@ -136,8 +716,83 @@ impl DrawingBackend for CairoBackend {
 
 	fn present(&mut self) {
 		self.ctx.pop_group_to_source();
-		self.clear();
+		self.ctx.save();
+		self.ctx.set_operator(cairo::Operator::Source);
+		if let Some(ref mut damage) = self.damage {
+			// Only replace the damaged areas, everything else keeps what was presented before
+			self.ctx.identity_matrix();
+			self.ctx.new_path();
+			for rect in damage.drain(..) {
+				self.ctx.rectangle(rect.x, rect.y, rect.width, rect.height);
+			}
+			self.ctx.clip();
+		}
+		self.ctx.paint();
+		self.ctx.restore();
 		self.surface.0.flush();
 		self.ctx.push_group();
+		// The transformation is part of the state saved by the group, so it has to be applied again for the new one
+		self.ctx.scale(self.scale, self.scale);
+	}
+
+	fn set_damage_tracking(&mut self, enabled: bool) {
+		if enabled != self.damage.is_some() {
+			self.damage = if enabled { Some(Vec::new()) } else { None };
+		}
+	}
+
+	fn damage(&self) -> &[Rect] {
+		self.damage.as_ref().map(|damage| damage.as_slice()).unwrap_or(&[])
 	}
+
+	fn present_and_sync(&mut self) {
+		self.present();
+		self.surface.0.flush();
+		unsafe {
+			let raw_surface = self.surface.0.to_raw_none();
+			let device = cairo_surface_get_device(raw_surface);
+			if !device.is_null() {
+				cairo_sys::cairo_device_flush(device);
+			}
+			// Flushing only sends the requests, so do a round-trip to make sure the server has processed them
+			match self.surface.0.get_type() {
+				cairo::SurfaceType::Xcb if !device.is_null() => {
+					let conn = xcb::Connection::from_raw_conn(cairo_sys::cairo_xcb_device_get_connection(device) as *mut _);
+					if let Err(e) = xcb::get_input_focus(&conn).get_reply() {
+						log::error!("Failed to sync XCB connection: {}", e);
+					}
+					// The connection is owned by the window backend
+					conn.into_raw_conn();
+				}
+				cairo::SurfaceType::Xlib => {
+					let display = cairo_sys::cairo_xlib_surface_get_display(raw_surface);
+					x11::xlib::XSync(display as *mut _, x11::xlib::False);
+				}
+				_ => {}
+			}
+		}
+	}
+}
+
+// Not bound by cairo-sys-rs 0.9
+extern "C" {
+	fn cairo_surface_get_device(surface: *mut cairo_sys::cairo_surface_t) -> *mut cairo_sys::cairo_device_t;
+}
+
+#[derive(Debug, Clone)]
+pub enum CairoBackendError {
+	Unsupported(&'static str),
+	/// Cairo couldn't create a surface.
+	SurfaceCreation(cairo::Status),
 }
+
+impl std::fmt::Display for CairoBackendError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			CairoBackendError::Unsupported(operation) => write!(f, "{} is not supported by this Cairo surface", operation),
+			CairoBackendError::SurfaceCreation(status) => write!(f, "failed to create a Cairo surface: {}", status),
+		}
+	}
+}
+
+impl std::error::Error for CairoBackendError {}
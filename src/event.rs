@@ -1,32 +1,249 @@
 use crate::window::WindowEvent;
 
+pub(crate) mod keysym;
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MouseMoveEvent {
 	pub pos: (f64, f64),
+	pub stylus: Option<StylusState>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MouseClickEvent {
 	pub state: PressState,
 	pub button: MouseButton,
 	pub pos: (f64, f64),
+	pub stylus: Option<StylusState>,
+	/// The modifier keys held when the button was pressed or released.
+	pub modifiers: Modifiers,
+}
+
+/// Extra state reported by pen tablets. Events from regular mice don't have this.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StylusState {
+	/// Pen pressure, from 0.0 to 1.0.
+	pub pressure: f64,
+	/// Tilt of the pen along the x and y axes, from -1.0 to 1.0.
+	pub tilt: (f64, f64),
 }
 
+/// A scroll wheel or touchpad scroll, reported the same way by every backend.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScrollEvent {
+	/// The horizontal and vertical scroll amount. Positive x scrolls right and positive y scrolls up (away from the
+	/// user).
+	pub delta: (f64, f64),
+	pub unit: ScrollUnit,
+	/// The position of the cursor in the window when scrolling, for finding what was scrolled.
+	pub pos: (f64, f64),
+}
+
+/// The unit of a scroll delta. Mouse wheels usually scroll by lines while touchpads scroll by pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScrollUnit {
+	Lines,
+	Pixels,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PressState {
 	Pressed,
 	Released,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseButton {
 	Left,
 	Right,
 	Middle,
+	/// Any other button, by its platform-specific number. On X11 the common back and forward side buttons are 8 and 9.
+	Other(u8),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Modifiers {
+	pub shift: bool,
+	pub ctrl: bool,
+	pub alt: bool,
+	pub logo: bool,
+	pub caps_lock: bool,
+	pub num_lock: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LockState {
+	pub caps: bool,
+	pub num: bool,
+}
+
+impl Modifiers {
+	pub fn lock_state(self) -> LockState {
+		LockState {
+			caps: self.caps_lock,
+			num: self.num_lock,
+		}
+	}
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeyboardEvent {
 	pub state: PressState,
-	pub keycode: winit::VirtualKeyCode,
+	/// The key after applying the keyboard layout.
+	pub key: Key,
+	/// The platform-specific code of the physical key, which doesn't depend on the keyboard layout. Useful for games
+	/// that bind to key positions rather than key labels. On XCB this is the X keycode.
+	pub scancode: u32,
+	pub modifiers: Modifiers,
+}
+
+/// A key on the keyboard, after applying the keyboard layout. This only covers the common keys; others are reported as
+/// `Unknown` and can be told apart by `KeyboardEvent::scancode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Key {
+	A,
+	B,
+	C,
+	D,
+	E,
+	F,
+	G,
+	H,
+	I,
+	J,
+	K,
+	L,
+	M,
+	N,
+	O,
+	P,
+	Q,
+	R,
+	S,
+	T,
+	U,
+	V,
+	W,
+	X,
+	Y,
+	Z,
+	Key0,
+	Key1,
+	Key2,
+	Key3,
+	Key4,
+	Key5,
+	Key6,
+	Key7,
+	Key8,
+	Key9,
+	Numpad0,
+	Numpad1,
+	Numpad2,
+	Numpad3,
+	Numpad4,
+	Numpad5,
+	Numpad6,
+	Numpad7,
+	Numpad8,
+	Numpad9,
+	F1,
+	F2,
+	F3,
+	F4,
+	F5,
+	F6,
+	F7,
+	F8,
+	F9,
+	F10,
+	F11,
+	F12,
+	Escape,
+	Enter,
+	Space,
+	Tab,
+	Backspace,
+	Delete,
+	Insert,
+	Home,
+	End,
+	PageUp,
+	PageDown,
+	Left,
+	Right,
+	Up,
+	Down,
+	Minus,
+	Equals,
+	Comma,
+	Period,
+	Slash,
+	Backslash,
+	Semicolon,
+	Apostrophe,
+	Grave,
+	LBracket,
+	RBracket,
+	LShift,
+	RShift,
+	LControl,
+	RControl,
+	LAlt,
+	RAlt,
+	LLogo,
+	RLogo,
+	CapsLock,
+	NumLock,
+	Unknown,
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn events_round_trip_through_json() {
+	let modifiers = Modifiers {
+		shift: true,
+		..Modifiers::default()
+	};
+	let events = vec![
+		WindowEvent::MouseMove(MouseMoveEvent {
+			pos: (1.5, 2.0),
+			stylus: Some(StylusState {
+				pressure: 0.5,
+				tilt: (0.25, -0.25),
+			}),
+		}),
+		WindowEvent::MouseClick(MouseClickEvent {
+			state: PressState::Pressed,
+			button: MouseButton::Other(8),
+			pos: (1.5, 2.0),
+			stylus: None,
+			modifiers,
+		}),
+		WindowEvent::Scroll(ScrollEvent {
+			delta: (0.0, -3.0),
+			unit: ScrollUnit::Lines,
+			pos: (1.5, 2.0),
+		}),
+		WindowEvent::Keyboard(KeyboardEvent {
+			state: PressState::Released,
+			key: Key::Enter,
+			scancode: 36,
+			modifiers,
+		}),
+		WindowEvent::ResizeHappened { dims: (640.0, 480.0) },
+		WindowEvent::CloseRequested,
+	];
+	let json = serde_json::to_string(&events).unwrap();
+	assert_eq!(serde_json::from_str::<Vec<WindowEvent>>(&json).unwrap(), events);
 }
@@ -0,0 +1,69 @@
+use crate::event::Key;
+
+// X11 and xkbcommon share keysym values, so backends on either translate keysyms into keys here.
+
+/// Maps a keysym to the key it represents, if it's one of the keys `Key` covers.
+pub(crate) fn keysym_to_key(keysym: u32) -> Option<Key> {
+	use crate::event::Key::*;
+
+	const LETTERS: [Key; 26] = [A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z];
+	const DIGITS: [Key; 10] = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+	const NUMPAD_DIGITS: [Key; 10] = [
+		Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+	];
+	const FUNCTION_KEYS: [Key; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+	Some(match keysym {
+		0x61..=0x7a => LETTERS[(keysym - 0x61) as usize],
+		0x41..=0x5a => LETTERS[(keysym - 0x41) as usize],
+		0x30..=0x39 => DIGITS[(keysym - 0x30) as usize],
+		0xffb0..=0xffb9 => NUMPAD_DIGITS[(keysym - 0xffb0) as usize],
+		0xffbe..=0xffc9 => FUNCTION_KEYS[(keysym - 0xffbe) as usize],
+		0x20 => Space,
+		0x27 => Apostrophe,
+		0x2c => Comma,
+		0x2d => Minus,
+		0x2e => Period,
+		0x2f => Slash,
+		0x3b => Semicolon,
+		0x3d => Equals,
+		0x5b => LBracket,
+		0x5c => Backslash,
+		0x5d => RBracket,
+		0x60 => Grave,
+		0xff08 => Backspace,
+		0xff09 => Tab,
+		0xff0d => Enter,
+		0xff1b => Escape,
+		0xff50 => Home,
+		0xff51 => Left,
+		0xff52 => Up,
+		0xff53 => Right,
+		0xff54 => Down,
+		0xff55 => PageUp,
+		0xff56 => PageDown,
+		0xff57 => End,
+		0xff63 => Insert,
+		0xff7f => NumLock,
+		0xff8d => Enter,
+		0xffe1 => LShift,
+		0xffe2 => RShift,
+		0xffe3 => LControl,
+		0xffe4 => RControl,
+		0xffe5 => CapsLock,
+		0xffe9 => LAlt,
+		0xffea => RAlt,
+		0xffeb => LLogo,
+		0xffec => RLogo,
+		0xffff => Delete,
+		_ => return None,
+	})
+}
+
+#[test]
+fn keysyms_map_to_keys() {
+	assert_eq!(keysym_to_key(0x61), Some(Key::A));
+	assert_eq!(keysym_to_key(0x41), Some(Key::A));
+	assert_eq!(keysym_to_key(0xffb1), Some(Key::Numpad1));
+	assert_eq!(keysym_to_key(0xff9c), None);
+}
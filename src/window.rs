@@ -1,35 +1,199 @@
+use crate::drawing::Rect;
 use crate::event::KeyboardEvent;
 use crate::event::MouseClickEvent;
 use crate::event::MouseMoveEvent;
+use crate::event::ScrollEvent;
 use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::hash::Hash;
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
 
+pub mod any;
+#[cfg(feature = "wayland")]
+pub mod wayland;
 pub mod winit;
 pub mod xcb;
 
 pub trait WindowBackend: Sized {
 	type Window;
+	type Id: Eq + Hash + Copy + Debug;
 	type Error: Debug;
 
 	fn init() -> Result<Self, Self::Error>;
 
 	fn create_window(&self, title: &str, dims: WindowDims) -> Result<Self::Window, Self::Error>;
 
-	fn get_window_events(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>);
+	/// Creates an undecorated, always-on-top window of the given size centered on the primary monitor, as used for splash
+	/// screens.
+	fn create_splash_window(&self, title: &str, dims: (u32, u32)) -> Result<Self::Window, Self::Error>;
 
-	fn set_window_size(&self, window: &Self::Window, dims: (u32, u32));
+	/// Gets an identifier for the window that is unique among the windows of this backend.
+	fn window_id(&self, window: &Self::Window) -> Result<Self::Id, Self::Error>;
+
+	fn get_window_events(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>) {
+		self.drain_events_immediate(window, &mut |evt| event_buf.push_back(evt));
+	}
+
+	/// Like `get_window_events`, but blocks until at least one event is available instead of returning immediately, so
+	/// event-driven programs can sleep while nothing is happening.
+	fn wait_events(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>) {
+		self.wait_events_timeout(window, event_buf, None);
+	}
+
+	/// Like `wait_events`, but gives up once `deadline` has passed even if no event arrived. `None` waits indefinitely.
+	fn wait_events_timeout(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>, deadline: Option<Instant>);
+
+	/// Translates pending events and passes each one to `f` as soon as it is read, without buffering. This is for
+	/// latency-sensitive input handling; events dispatched this way bypass `App`, so it won't see resizes or track input
+	/// state for them.
+	fn drain_events_immediate(&self, window: &mut Self::Window, f: &mut dyn FnMut(WindowEvent));
+
+	/// Requests a new size for the window's client area. Window managers may override the request for managed windows,
+	/// so read the final size back with `get_window_size` (or wait for `ResizeHappened`) instead of assuming it.
+	fn set_window_size(&self, window: &Self::Window, dims: (u32, u32)) -> Result<(), Self::Error>;
 
 	fn set_window_position(&self, window: &Self::Window, position: (i32, i32)) -> Result<(), Self::Error>;
 
+	/// Shows or hides the title bar and borders the window manager draws around the window. On X11 this goes through the
+	/// Motif hints, which most but not all window managers honor, and some only apply the change when the window is next
+	/// mapped.
+	fn set_decorations(&self, window: &Self::Window, decorated: bool) -> Result<(), Self::Error>;
+
+	/// Tells the window manager the limits of the window's client area: the minimum and maximum size in physical pixels,
+	/// and the minimum and maximum aspect ratio (width divided by height). Pass the same ratio twice to keep it fixed.
+	/// `None` removes a limit.
+	fn set_size_hints(
+		&self,
+		window: &Self::Window,
+		min: Option<(u32, u32)>,
+		max: Option<(u32, u32)>,
+		aspect: Option<(f64, f64)>,
+	) -> Result<(), Self::Error>;
+
 	fn get_window_size(&self, window: &Self::Window) -> Result<(u32, u32), Self::Error>;
 
-	fn is_window_open(&self, window: &Self::Window);
+	/// Gets the screen position of the top left corner of the window's client area, below any decorations the window
+	/// manager added. Window managers usually place the decorations at the position given to `set_window_position`, so
+	/// use `move_by` for relative moves rather than adding to this position.
+	fn get_window_position(&self, window: &Self::Window) -> Result<(i32, i32), Self::Error>;
+
+	/// Gets the area of the monitor nearest to the window that isn't covered by panels or docks, in screen coordinates.
+	fn get_work_area(&self, window: &Self::Window) -> Result<WindowDims, Self::Error>;
+
+	/// Moves the window relative to its current position.
+	fn move_by(&self, window: &Self::Window, dx: i32, dy: i32) -> Result<(), Self::Error> {
+		let (x, y) = self.get_window_position(window)?;
+		self.set_window_position(window, (x + dx, y + dy))
+	}
+
+	/// Resizes the window relative to its current size. The resulting size is clamped to at least 1x1.
+	fn resize_by(&self, window: &Self::Window, dw: i32, dh: i32) -> Result<(), Self::Error> {
+		let (width, height) = self.get_window_size(window)?;
+		self.set_window_size(window, resize_dims((width, height), dw, dh))
+	}
+
+	/// Asks for the window to be raised and focused. Window managers may refuse this to prevent focus stealing, for example
+	/// by marking the window as demanding attention instead.
+	fn activate(&self, window: &Self::Window) -> Result<(), Self::Error>;
+
+	/// Sets whether the window should be left out of taskbars.
+	fn set_skip_taskbar(&self, window: &Self::Window, skip: bool) -> Result<(), Self::Error>;
+
+	/// Sets whether the window should be left out of pagers and workspace switchers.
+	fn set_skip_pager(&self, window: &Self::Window, skip: bool) -> Result<(), Self::Error>;
+
+	/// Sets whether the window should be kept above other windows.
+	fn set_always_on_top(&self, window: &Self::Window, on_top: bool) -> Result<(), Self::Error>;
+
+	/// Tells the window manager what kind of window this is so it can place and decorate it accordingly. Best set
+	/// before the window is first mapped.
+	fn set_window_type(&self, window: &Self::Window, ty: WindowType) -> Result<(), Self::Error>;
+
+	/// Sets how opaque the whole window is, from 0.0 (invisible) to 1.0 (opaque). Only has an effect under a compositing
+	/// window manager.
+	fn set_opacity(&self, window: &Self::Window, opacity: f64) -> Result<(), Self::Error>;
+
+	/// Whether the window still exists. Backends that can't tell when a window is destroyed report it as closed once
+	/// closing it was requested.
+	fn is_window_open(&self, window: &Self::Window) -> Result<bool, Self::Error>;
+
+	/// Changes the title shown in the window's title bar and taskbar entry.
+	fn set_window_title(&self, window: &Self::Window, title: &str) -> Result<(), Self::Error>;
+
+	/// Makes the window cover its whole monitor without decorations, or restores it. On XCB this needs an EWMH-compliant
+	/// window manager.
+	fn set_fullscreen(&self, window: &Self::Window, fullscreen: bool) -> Result<(), Self::Error>;
+
+	/// Minimizes (iconifies) the window, or restores it from being minimized.
+	fn set_minimized(&self, window: &Self::Window, minimized: bool) -> Result<(), Self::Error>;
+
+	/// Maximizes the window to fill the work area, or restores its previous size.
+	fn set_maximized(&self, window: &Self::Window, maximized: bool) -> Result<(), Self::Error>;
+
+	/// Gets the ratio of physical pixels to logical pixels for the window, e.g. 2.0 on a typical HiDPI display. All sizes
+	/// and positions in this trait are in physical pixels.
+	fn get_scale_factor(&self, window: &Self::Window) -> Result<f64, Self::Error>;
 
 	fn present(&self);
 
 	fn close(&self, window: Self::Window);
 }
 
+/// Sleeps until `fd` has data to read or `deadline` passes, returning false if the deadline passed first. Being
+/// interrupted by a signal counts as readable, so callers should check for data and the deadline again afterwards.
+pub(crate) fn poll_readable(fd: RawFd, deadline: Option<Instant>) -> bool {
+	let timeout = match deadline {
+		Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+			// Round up so the wait doesn't end just before the deadline
+			Some(remaining) if remaining > Duration::from_secs(0) => {
+				((remaining.as_micros() + 999) / 1000).min(libc::c_int::max_value() as u128) as libc::c_int
+			}
+			_ => return false,
+		},
+		None => -1,
+	};
+	let mut pollfd = libc::pollfd {
+		fd,
+		events: libc::POLLIN,
+		revents: 0,
+	};
+	unsafe { libc::poll(&mut pollfd, 1, timeout) != 0 }
+}
+
+#[test]
+fn poll_readable_times_out_without_data() {
+	let mut fds = [0; 2];
+	assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+	let start = Instant::now();
+	assert!(!poll_readable(fds[0], Some(start + Duration::from_millis(20))));
+	assert!(start.elapsed() >= Duration::from_millis(20));
+
+	assert_eq!(unsafe { libc::write(fds[1], b"x".as_ptr() as *const libc::c_void, 1) }, 1);
+	assert!(poll_readable(fds[0], Some(Instant::now() + Duration::from_secs(5))));
+	unsafe {
+		libc::close(fds[0]);
+		libc::close(fds[1]);
+	}
+}
+
+pub(crate) fn resize_dims(dims: (u32, u32), dw: i32, dh: i32) -> (u32, u32) {
+	(
+		(dims.0 as i64 + dw as i64).max(1) as u32,
+		(dims.1 as i64 + dh as i64).max(1) as u32,
+	)
+}
+
+/// The role of a window, used by window managers and compositors to pick its placement, decorations and stacking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowType {
+	Normal,
+	Dialog,
+	Menu,
+	Tooltip,
+	Utility,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct WindowDims {
 	pub x: i32,
@@ -38,15 +202,71 @@ pub struct WindowDims {
 	pub height: u32,
 }
 
+impl WindowDims {
+	/// Gets the overlapping part of two rectangles, or `None` if they don't overlap.
+	pub fn intersection(&self, other: &WindowDims) -> Option<WindowDims> {
+		let x = self.x.max(other.x);
+		let y = self.y.max(other.y);
+		let right = (self.x + self.width as i32).min(other.x + other.width as i32);
+		let bottom = (self.y + self.height as i32).min(other.y + other.height as i32);
+		if right <= x || bottom <= y {
+			return None;
+		}
+		Some(WindowDims {
+			x,
+			y,
+			width: (right - x) as u32,
+			height: (bottom - y) as u32,
+		})
+	}
+
+	/// Gets the squared distance from a point to the nearest point of the rectangle, which is zero if it's inside.
+	pub(crate) fn distance_squared_to(&self, point: (i32, i32)) -> i64 {
+		let dx = (self.x - point.0).max(point.0 - (self.x + self.width as i32)).max(0) as i64;
+		let dy = (self.y - point.1).max(point.1 - (self.y + self.height as i32)).max(0) as i64;
+		dx * dx + dy * dy
+	}
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WindowEvent {
 	CloseRequested,
 	CloseHappened,
-	ResizeHappened { dims: (f64, f64) },
+	ResizeHappened {
+		dims: (f64, f64),
+	},
 	MouseMove(MouseMoveEvent),
 	MouseClick(MouseClickEvent),
+	Scroll(ScrollEvent),
 	MouseEnter,
 	MouseExit,
+	FocusGained,
+	FocusLost,
 	Keyboard(KeyboardEvent),
-	Expose,
+	ClipboardChanged,
+	/// Part of the window needs to be redrawn. `more_coming` is set when further expose events immediately follow, so
+	/// redrawing can be deferred until the last one of a burst.
+	Expose {
+		region: Rect,
+		more_coming: bool,
+	},
+	/// The window was mapped, e.g. after being restored from minimized.
+	Shown,
+	/// The window was unmapped, e.g. by being minimized. Nothing drawn while it's hidden will be visible.
+	Hidden,
+	/// The window moved to a display with a different scale factor, or the display's scale factor changed.
+	ScaleFactorChanged {
+		scale: f64,
+	},
+	/// The pointer moved into the hit region with this id, as set with `App::set_hit_regions`. Always delivered right after
+	/// the `MouseMove` that caused it, following the `RegionExit` of the previously hovered region.
+	RegionEnter(u32),
+	/// The pointer left the hit region with this id, either by moving or by leaving the window.
+	RegionExit(u32),
+	/// A new frame of `App::run` is starting, `delta` after the previous one. Delivered once per iteration, after the
+	/// other events, so this is the place to advance animations and draw.
+	Frame {
+		delta: Duration,
+	},
 }
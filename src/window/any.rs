@@ -0,0 +1,248 @@
+use crate::drawing::cairo::{CairoBackend, CairoSurface};
+use crate::drawing::SurfaceCreator;
+use crate::window::winit::{WinitBackend, WinitBackendError, WinitWindow};
+use crate::window::xcb::{XcbBackend, XcbBackendError, XcbWindow};
+use crate::window::{WindowBackend, WindowDims, WindowEvent, WindowType};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// A window backend chosen at runtime. `init` tries the XCB backend first and falls back to winit if connecting to the X
+/// server fails. Every operation is forwarded to the selected backend by matching on the variant.
+pub enum AnyBackend {
+	Xcb(XcbBackend),
+	Winit(WinitBackend),
+}
+
+pub enum AnyWindow {
+	Xcb(XcbWindow),
+	Winit(WinitWindow),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnyWindowId {
+	Xcb(xcb::Window),
+	Winit(winit::WindowId),
+}
+
+#[derive(Debug)]
+pub enum AnyBackendError {
+	Xcb(XcbBackendError),
+	Winit(WinitBackendError),
+	/// A window was passed to a different backend than the one that created it.
+	WindowMismatch,
+}
+
+impl std::fmt::Display for AnyBackendError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			AnyBackendError::Xcb(e) => write!(f, "{}", e),
+			AnyBackendError::Winit(e) => write!(f, "{}", e),
+			AnyBackendError::WindowMismatch => write!(f, "window belongs to a different backend"),
+		}
+	}
+}
+
+impl std::error::Error for AnyBackendError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			AnyBackendError::Xcb(e) => e.source(),
+			AnyBackendError::Winit(e) => e.source(),
+			AnyBackendError::WindowMismatch => None,
+		}
+	}
+}
+
+/// Forwards a fallible operation on a window to the backend that created it.
+macro_rules! dispatch {
+	($self:expr, $window:expr, |$backend:ident, $inner:ident| $body:expr) => {
+		match ($self, $window) {
+			(AnyBackend::Xcb($backend), AnyWindow::Xcb($inner)) => $body.map_err(AnyBackendError::Xcb),
+			(AnyBackend::Winit($backend), AnyWindow::Winit($inner)) => $body.map_err(AnyBackendError::Winit),
+			_ => Err(AnyBackendError::WindowMismatch),
+		}
+	};
+}
+
+impl WindowBackend for AnyBackend {
+	type Window = AnyWindow;
+	type Id = AnyWindowId;
+	type Error = AnyBackendError;
+
+	fn init() -> Result<Self, Self::Error> {
+		match XcbBackend::init() {
+			Ok(backend) => Ok(AnyBackend::Xcb(backend)),
+			Err(e) => {
+				log::info!("Failed to initialize XCB backend, falling back to winit: {:?}", e);
+				WinitBackend::init().map(AnyBackend::Winit).map_err(AnyBackendError::Winit)
+			}
+		}
+	}
+
+	fn create_window(&self, title: &str, dims: WindowDims) -> Result<Self::Window, Self::Error> {
+		match self {
+			AnyBackend::Xcb(backend) => WindowBackend::create_window(backend, title, dims)
+				.map(AnyWindow::Xcb)
+				.map_err(AnyBackendError::Xcb),
+			AnyBackend::Winit(backend) => backend
+				.create_window(title, dims)
+				.map(AnyWindow::Winit)
+				.map_err(AnyBackendError::Winit),
+		}
+	}
+
+	fn create_splash_window(&self, title: &str, dims: (u32, u32)) -> Result<Self::Window, Self::Error> {
+		match self {
+			AnyBackend::Xcb(backend) => backend
+				.create_splash_window(title, dims)
+				.map(AnyWindow::Xcb)
+				.map_err(AnyBackendError::Xcb),
+			AnyBackend::Winit(backend) => backend
+				.create_splash_window(title, dims)
+				.map(AnyWindow::Winit)
+				.map_err(AnyBackendError::Winit),
+		}
+	}
+
+	fn window_id(&self, window: &Self::Window) -> Result<Self::Id, Self::Error> {
+		match (self, window) {
+			(AnyBackend::Xcb(backend), AnyWindow::Xcb(window)) => {
+				backend.window_id(window).map(AnyWindowId::Xcb).map_err(AnyBackendError::Xcb)
+			}
+			(AnyBackend::Winit(backend), AnyWindow::Winit(window)) => backend
+				.window_id(window)
+				.map(AnyWindowId::Winit)
+				.map_err(AnyBackendError::Winit),
+			_ => Err(AnyBackendError::WindowMismatch),
+		}
+	}
+
+	fn drain_events_immediate(&self, window: &mut Self::Window, f: &mut dyn FnMut(WindowEvent)) {
+		match (self, window) {
+			(AnyBackend::Xcb(backend), AnyWindow::Xcb(window)) => backend.drain_events_immediate(window, f),
+			(AnyBackend::Winit(backend), AnyWindow::Winit(window)) => backend.drain_events_immediate(window, f),
+			_ => log::error!("Attempted to get events for a window that was not created by this backend"),
+		}
+	}
+
+	fn wait_events_timeout(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>, deadline: Option<Instant>) {
+		match (self, window) {
+			(AnyBackend::Xcb(backend), AnyWindow::Xcb(window)) => backend.wait_events_timeout(window, event_buf, deadline),
+			(AnyBackend::Winit(backend), AnyWindow::Winit(window)) => backend.wait_events_timeout(window, event_buf, deadline),
+			_ => log::error!("Attempted to wait for events for a window that was not created by this backend"),
+		}
+	}
+
+	fn set_window_size(&self, window: &Self::Window, dims: (u32, u32)) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_window_size(window, dims))
+	}
+
+	fn set_window_position(&self, window: &Self::Window, position: (i32, i32)) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_window_position(window, position))
+	}
+
+	fn set_decorations(&self, window: &Self::Window, decorated: bool) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_decorations(window, decorated))
+	}
+
+	fn set_size_hints(
+		&self,
+		window: &Self::Window,
+		min: Option<(u32, u32)>,
+		max: Option<(u32, u32)>,
+		aspect: Option<(f64, f64)>,
+	) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend
+			.set_size_hints(window, min, max, aspect))
+	}
+
+	fn get_window_size(&self, window: &Self::Window) -> Result<(u32, u32), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.get_window_size(window))
+	}
+
+	fn get_window_position(&self, window: &Self::Window) -> Result<(i32, i32), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.get_window_position(window))
+	}
+
+	fn get_work_area(&self, window: &Self::Window) -> Result<WindowDims, Self::Error> {
+		dispatch!(self, window, |backend, window| backend.get_work_area(window))
+	}
+
+	fn move_by(&self, window: &Self::Window, dx: i32, dy: i32) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.move_by(window, dx, dy))
+	}
+
+	fn resize_by(&self, window: &Self::Window, dw: i32, dh: i32) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.resize_by(window, dw, dh))
+	}
+
+	fn activate(&self, window: &Self::Window) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.activate(window))
+	}
+
+	fn set_skip_taskbar(&self, window: &Self::Window, skip: bool) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_skip_taskbar(window, skip))
+	}
+
+	fn set_skip_pager(&self, window: &Self::Window, skip: bool) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_skip_pager(window, skip))
+	}
+
+	fn set_always_on_top(&self, window: &Self::Window, on_top: bool) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_always_on_top(window, on_top))
+	}
+
+	fn set_window_type(&self, window: &Self::Window, ty: WindowType) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_window_type(window, ty))
+	}
+
+	fn set_opacity(&self, window: &Self::Window, opacity: f64) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_opacity(window, opacity))
+	}
+
+	fn is_window_open(&self, window: &Self::Window) -> Result<bool, Self::Error> {
+		dispatch!(self, window, |backend, window| backend.is_window_open(window))
+	}
+
+	fn set_window_title(&self, window: &Self::Window, title: &str) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_window_title(window, title))
+	}
+
+	fn set_fullscreen(&self, window: &Self::Window, fullscreen: bool) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_fullscreen(window, fullscreen))
+	}
+
+	fn set_minimized(&self, window: &Self::Window, minimized: bool) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_minimized(window, minimized))
+	}
+
+	fn set_maximized(&self, window: &Self::Window, maximized: bool) -> Result<(), Self::Error> {
+		dispatch!(self, window, |backend, window| backend.set_maximized(window, maximized))
+	}
+
+	fn get_scale_factor(&self, window: &Self::Window) -> Result<f64, Self::Error> {
+		dispatch!(self, window, |backend, window| backend.get_scale_factor(window))
+	}
+
+	fn present(&self) {
+		match self {
+			AnyBackend::Xcb(backend) => backend.present(),
+			AnyBackend::Winit(backend) => backend.present(),
+		}
+	}
+
+	fn close(&self, window: Self::Window) {
+		match (self, window) {
+			(AnyBackend::Xcb(backend), AnyWindow::Xcb(window)) => backend.close(window),
+			(AnyBackend::Winit(backend), AnyWindow::Winit(window)) => backend.close(window),
+			_ => log::error!("Attempted to close a window that was not created by this backend"),
+		}
+	}
+}
+
+impl SurfaceCreator<Self, CairoBackend> for AnyBackend {
+	type Error = AnyBackendError;
+
+	fn create_surface(&self, args: &AnyWindow) -> Result<CairoSurface, AnyBackendError> {
+		dispatch!(self, args, |backend, window| backend.create_surface(window))
+	}
+}
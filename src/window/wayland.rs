@@ -0,0 +1,746 @@
+use crate::window::{poll_readable, WindowBackend, WindowDims, WindowEvent, WindowType};
+
+use crate::drawing::cairo::CairoBackend;
+use crate::drawing::cairo::CairoSurface;
+use crate::drawing::SurfaceCreator;
+use crate::event::keysym::keysym_to_key;
+use crate::event::Key;
+use crate::event::KeyboardEvent;
+use crate::event::Modifiers;
+use crate::event::MouseButton;
+use crate::event::MouseClickEvent;
+use crate::event::MouseMoveEvent;
+use crate::event::PressState;
+use crate::event::ScrollEvent;
+use crate::event::ScrollUnit;
+use smithay_client_toolkit as sctk;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use sctk::keyboard::{self, KeyState};
+use sctk::reexports::client::protocol::wl_pointer;
+use sctk::reexports::client::protocol::wl_seat::{self, RequestsTrait as SeatRequests};
+use sctk::reexports::client::protocol::wl_shm;
+use sctk::reexports::client::protocol::wl_surface::{self, RequestsTrait as SurfaceRequests};
+use sctk::reexports::client::{self, ConnectError, Display, EventQueue, Proxy};
+use sctk::utils::DoubleMemPool;
+use sctk::window::{ConceptFrame, Event as ShellEvent, Window};
+use sctk::Environment;
+
+/// Linux input event codes for the mouse buttons, as sent in `wl_pointer.button` events.
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_TASK: u32 = 0x117;
+
+pub struct WaylandWindow {
+	window: RefCell<Window<ConceptFrame>>,
+	id: u32,
+}
+
+/// A window backend for Wayland compositors supporting `xdg_shell`, with client-side decorations drawn by
+/// smithay-client-toolkit.
+///
+/// Wayland surfaces get their contents from shared memory buffers, so drawing goes to an image surface that `present`
+/// copies into a buffer and commits. Wayland doesn't let clients position their windows or read their position, so the
+/// methods for those return `Unsupported`.
+pub struct WaylandBackend {
+	display: Display,
+	event_queue: RefCell<EventQueue>,
+	env: Environment,
+	seat: Option<Proxy<wl_seat::WlSeat>>,
+	shared: Arc<Mutex<SharedState>>,
+	targets: RefCell<HashMap<u32, PresentTarget>>,
+}
+
+/// State updated by the protocol callbacks, which run during dispatch and have to be `Send`.
+#[derive(Default)]
+struct SharedState {
+	windows: HashMap<u32, WindowState>,
+	pointer_focus: Option<u32>,
+	keyboard_focus: Option<u32>,
+	pointer_pos: (f64, f64),
+	modifiers: Modifiers,
+}
+
+struct WindowState {
+	events: VecDeque<WindowEvent>,
+	/// The size of the window's contents in surface coordinates, which are scaled by `scale` to get physical pixels.
+	size: (u32, u32),
+	scale: i32,
+	/// Whether the compositor has sent the first configure event. Attaching a buffer before that is a protocol error.
+	configured: bool,
+	/// Set when the decorations have to be resized or redrawn, which needs the `Window` and so waits for the next drain.
+	frame_outdated: bool,
+	closed: bool,
+}
+
+/// What `present` needs to copy a window's drawing into a buffer.
+struct PresentTarget {
+	surface: Proxy<wl_surface::WlSurface>,
+	pool: DoubleMemPool,
+	image: Option<cairo::ImageSurface>,
+}
+
+impl SharedState {
+	fn push_event(&mut self, id: u32, evt: WindowEvent) {
+		if let Some(state) = self.windows.get_mut(&id) {
+			state.events.push_back(evt);
+		}
+	}
+
+	fn handle_shell_event(&mut self, id: u32, evt: ShellEvent) {
+		let state = match self.windows.get_mut(&id) {
+			Some(state) => state,
+			None => return,
+		};
+		match evt {
+			ShellEvent::Configure { new_size, .. } => {
+				state.configured = true;
+				state.frame_outdated = true;
+				// The compositor leaves the size up to us when it doesn't send one
+				if let Some(new_size) = new_size {
+					let new_size = (new_size.0.max(1), new_size.1.max(1));
+					if new_size != state.size {
+						state.size = new_size;
+						let dims = state.physical_size();
+						state.events.push_back(WindowEvent::ResizeHappened {
+							dims: (dims.0 as f64, dims.1 as f64),
+						});
+					}
+				}
+			}
+			ShellEvent::Refresh => state.frame_outdated = true,
+			ShellEvent::Close => {
+				state.closed = true;
+				state.events.push_back(WindowEvent::CloseRequested);
+			}
+		}
+	}
+
+	fn handle_scale_change(&mut self, id: u32, scale: i32) {
+		let state = match self.windows.get_mut(&id) {
+			Some(state) => state,
+			None => return,
+		};
+		if scale == state.scale {
+			return;
+		}
+		state.scale = scale;
+		let dims = state.physical_size();
+		state
+			.events
+			.push_back(WindowEvent::ScaleFactorChanged { scale: scale as f64 });
+		state.events.push_back(WindowEvent::ResizeHappened {
+			dims: (dims.0 as f64, dims.1 as f64),
+		});
+	}
+
+	fn handle_pointer_event(&mut self, evt: wl_pointer::Event) {
+		match evt {
+			wl_pointer::Event::Enter {
+				surface,
+				surface_x,
+				surface_y,
+				..
+			} => {
+				// Entering the decorations, which are separate surfaces, counts as leaving the window
+				if !self.windows.contains_key(&surface.id()) {
+					return;
+				}
+				self.pointer_focus = Some(surface.id());
+				self.push_event(surface.id(), WindowEvent::MouseEnter);
+				self.handle_pointer_motion(surface_x, surface_y);
+			}
+			wl_pointer::Event::Leave { surface, .. } if self.pointer_focus == Some(surface.id()) => {
+				self.pointer_focus = None;
+				self.push_event(surface.id(), WindowEvent::MouseExit);
+			}
+			wl_pointer::Event::Motion {
+				surface_x, surface_y, ..
+			} => self.handle_pointer_motion(surface_x, surface_y),
+			wl_pointer::Event::Button { button, state, .. } => {
+				let id = match self.pointer_focus {
+					Some(id) => id,
+					None => return,
+				};
+				let button = match button {
+					BTN_LEFT => MouseButton::Left,
+					BTN_RIGHT => MouseButton::Right,
+					BTN_MIDDLE => MouseButton::Middle,
+					// Number the extra buttons after the scroll buttons, the way X11 does
+					BTN_SIDE..=BTN_TASK => MouseButton::Other((button - BTN_SIDE + 8) as u8),
+					_ => return,
+				};
+				let evt = WindowEvent::MouseClick(MouseClickEvent {
+					state: match state {
+						wl_pointer::ButtonState::Pressed => PressState::Pressed,
+						wl_pointer::ButtonState::Released => PressState::Released,
+					},
+					button,
+					pos: self.pointer_pos,
+					stylus: None,
+					modifiers: self.modifiers,
+				});
+				self.push_event(id, evt);
+			}
+			wl_pointer::Event::Axis { axis, value, .. } => {
+				let id = match self.pointer_focus {
+					Some(id) => id,
+					None => return,
+				};
+				let scale = self.windows[&id].scale as f64;
+				// Wayland axis values are positive to the right and downwards
+				let delta = match axis {
+					wl_pointer::Axis::HorizontalScroll => (value * scale, 0.0),
+					wl_pointer::Axis::VerticalScroll => (0.0, -value * scale),
+				};
+				let evt = WindowEvent::Scroll(ScrollEvent {
+					delta,
+					unit: ScrollUnit::Pixels,
+					pos: self.pointer_pos,
+				});
+				self.push_event(id, evt);
+			}
+			_ => {}
+		}
+	}
+
+	fn handle_pointer_motion(&mut self, surface_x: f64, surface_y: f64) {
+		let id = match self.pointer_focus {
+			Some(id) => id,
+			None => return,
+		};
+		let scale = self.windows[&id].scale as f64;
+		self.pointer_pos = (surface_x * scale, surface_y * scale);
+		let evt = WindowEvent::MouseMove(MouseMoveEvent {
+			pos: self.pointer_pos,
+			stylus: None,
+		});
+		self.push_event(id, evt);
+	}
+
+	fn handle_keyboard_event(&mut self, evt: keyboard::Event) {
+		match evt {
+			keyboard::Event::Enter { surface, .. } if self.windows.contains_key(&surface.id()) => {
+				self.keyboard_focus = Some(surface.id());
+				self.push_event(surface.id(), WindowEvent::FocusGained);
+			}
+			keyboard::Event::Leave { surface, .. } if self.keyboard_focus == Some(surface.id()) => {
+				self.keyboard_focus = None;
+				self.push_event(surface.id(), WindowEvent::FocusLost);
+			}
+			keyboard::Event::Key {
+				rawkey, keysym, state, ..
+			} => {
+				let id = match self.keyboard_focus {
+					Some(id) => id,
+					None => return,
+				};
+				// xkbcommon keysyms have the same values as X11 keysyms
+				let evt = WindowEvent::Keyboard(KeyboardEvent {
+					state: match state {
+						KeyState::Pressed => PressState::Pressed,
+						KeyState::Released => PressState::Released,
+					},
+					key: keysym_to_key(keysym).unwrap_or(Key::Unknown),
+					scancode: rawkey,
+					modifiers: self.modifiers,
+				});
+				self.push_event(id, evt);
+			}
+			keyboard::Event::Modifiers { modifiers } => {
+				self.modifiers = Modifiers {
+					shift: modifiers.shift,
+					ctrl: modifiers.ctrl,
+					alt: modifiers.alt,
+					logo: modifiers.logo,
+					caps_lock: modifiers.caps_lock,
+					num_lock: modifiers.num_lock,
+				};
+			}
+			_ => {}
+		}
+	}
+}
+
+impl WindowState {
+	fn physical_size(&self) -> (u32, u32) {
+		(self.size.0 * self.scale as u32, self.size.1 * self.scale as u32)
+	}
+}
+
+impl PresentTarget {
+	/// Copies the visible part of the image surface into a free buffer and commits it. Does nothing if the compositor
+	/// still holds both buffers, in which case the frame is dropped.
+	fn present(&mut self, state: &WindowState) -> Result<(), WaylandBackendError> {
+		let image = match self.image {
+			Some(ref image) => image,
+			None => return Ok(()),
+		};
+		let pool = match self.pool.pool() {
+			Some(pool) => pool,
+			None => return Ok(()),
+		};
+
+		// Buffer sizes have to be a multiple of the buffer scale
+		let scale = state.scale;
+		let (width, height) = state.physical_size();
+		let width = (width as i32).min(image.get_width()) / scale * scale;
+		let height = (height as i32).min(image.get_height()) / scale * scale;
+		if width == 0 || height == 0 {
+			return Ok(());
+		}
+		let stride = width as usize * 4;
+		pool.resize(stride * height as usize).map_err(WaylandBackendError::IoError)?;
+
+		image.flush();
+		let src_stride = image.get_stride() as usize;
+		// The backend's Cairo context holds another reference to the surface, so get_data would refuse to borrow it
+		let src = unsafe {
+			std::slice::from_raw_parts(
+				cairo_sys::cairo_image_surface_get_data(image.to_raw_none()),
+				src_stride * image.get_height() as usize,
+			)
+		};
+		let dst = pool.mmap();
+		for row in 0..height as usize {
+			dst[row * stride..(row + 1) * stride].copy_from_slice(&src[row * src_stride..row * src_stride + stride]);
+		}
+
+		// Cairo's ARGB32 is native-endian, same as wl_shm's ARGB8888
+		let buffer = pool.buffer(0, width, height, stride as i32, wl_shm::Format::Argb8888);
+		self.surface.set_buffer_scale(scale);
+		self.surface.attach(Some(&buffer), 0, 0);
+		self.surface.damage(0, 0, width / scale, height / scale);
+		self.surface.commit();
+		Ok(())
+	}
+}
+
+impl WaylandBackend {
+	/// Reads and dispatches whatever events the compositor has sent, without blocking.
+	fn dispatch(&self) -> Result<(), WaylandBackendError> {
+		let mut event_queue = self.event_queue.borrow_mut();
+		self.display.flush().map_err(WaylandBackendError::IoError)?;
+		if let Some(guard) = event_queue.prepare_read() {
+			if let Err(e) = guard.read_events() {
+				if e.kind() != std::io::ErrorKind::WouldBlock {
+					return Err(WaylandBackendError::IoError(e));
+				}
+			}
+		}
+		event_queue.dispatch_pending().map_err(WaylandBackendError::IoError)?;
+		Ok(())
+	}
+
+	fn with_window_state<T>(
+		&self,
+		window: &WaylandWindow,
+		f: impl FnOnce(&mut WindowState) -> T,
+	) -> Result<T, WaylandBackendError> {
+		let mut shared = self.shared.lock().unwrap();
+		let state = shared
+			.windows
+			.get_mut(&window.id)
+			.ok_or(WaylandBackendError::WindowMismatch)?;
+		Ok(f(state))
+	}
+}
+
+impl WindowBackend for WaylandBackend {
+	type Window = WaylandWindow;
+	type Id = u32;
+	type Error = WaylandBackendError;
+
+	fn init() -> Result<Self, Self::Error> {
+		let (display, mut event_queue) = Display::connect_to_env().map_err(WaylandBackendError::ConnectError)?;
+		let env = Environment::from_display(&display, &mut event_queue).map_err(WaylandBackendError::IoError)?;
+		let shared = Arc::new(Mutex::new(SharedState::default()));
+
+		// Only the first seat is used, which is all most setups have
+		let seat_shared = shared.clone();
+		let mut has_pointer = false;
+		let mut has_keyboard = false;
+		let seat = env
+			.manager
+			.instantiate_auto::<wl_seat::WlSeat, _>(|seat| {
+				seat.implement(
+					move |evt, seat: Proxy<wl_seat::WlSeat>| {
+						let capabilities = match evt {
+							wl_seat::Event::Capabilities { capabilities } => capabilities,
+							_ => return,
+						};
+						if !has_pointer && capabilities.contains(wl_seat::Capability::Pointer) {
+							has_pointer = true;
+							let shared = seat_shared.clone();
+							seat.get_pointer(|pointer| {
+								pointer.implement(move |evt, _| shared.lock().unwrap().handle_pointer_event(evt), ())
+							})
+							.unwrap();
+						}
+						if !has_keyboard && capabilities.contains(wl_seat::Capability::Keyboard) {
+							has_keyboard = true;
+							let shared = seat_shared.clone();
+							if let Err(e) =
+								keyboard::map_keyboard_auto(&seat, move |evt, _| shared.lock().unwrap().handle_keyboard_event(evt))
+							{
+								log::error!("Failed to set up the keyboard: {:?}", e);
+							}
+						}
+					},
+					(),
+				)
+			})
+			.map_err(|e| {
+				log::warn!("No seat available, the window won't get any input: {:?}", e);
+			})
+			.ok();
+		event_queue.sync_roundtrip().map_err(WaylandBackendError::IoError)?;
+
+		Ok(WaylandBackend {
+			display,
+			event_queue: RefCell::new(event_queue),
+			env,
+			seat,
+			shared,
+			targets: RefCell::new(HashMap::new()),
+		})
+	}
+
+	/// Creates a window with the size in `dims`. The position is ignored, since Wayland leaves window placement to the
+	/// compositor.
+	fn create_window(&self, title: &str, dims: WindowDims) -> Result<Self::Window, Self::Error> {
+		let dpi_shared = self.shared.clone();
+		let surface = self
+			.env
+			.create_surface(move |scale, surface| dpi_shared.lock().unwrap().handle_scale_change(surface.id(), scale));
+		let id = surface.id();
+		let size = (dims.width.max(1), dims.height.max(1));
+		self.shared.lock().unwrap().windows.insert(
+			id,
+			WindowState {
+				events: VecDeque::new(),
+				size,
+				scale: 1,
+				configured: false,
+				frame_outdated: true,
+				closed: false,
+			},
+		);
+
+		let shell_shared = self.shared.clone();
+		let mut window = Window::<ConceptFrame>::init_from_env(&self.env, surface.clone(), size, move |evt| {
+			shell_shared.lock().unwrap().handle_shell_event(id, evt)
+		})
+		.map_err(WaylandBackendError::IoError)?;
+		window.set_title(title.to_owned());
+		if let Some(ref seat) = self.seat {
+			window.new_seat(seat);
+		}
+
+		let pool = DoubleMemPool::new(&self.env.shm, || {}).map_err(WaylandBackendError::IoError)?;
+		self.targets.borrow_mut().insert(
+			id,
+			PresentTarget {
+				surface,
+				pool,
+				image: None,
+			},
+		);
+
+		Ok(WaylandWindow {
+			window: RefCell::new(window),
+			id,
+		})
+	}
+
+	/// Creates an undecorated, fixed-size window. Wayland has no way to center a window or keep it on top, so unlike the
+	/// other backends the compositor decides where it goes.
+	fn create_splash_window(&self, title: &str, dims: (u32, u32)) -> Result<Self::Window, Self::Error> {
+		let window = WindowBackend::create_window(
+			self,
+			title,
+			WindowDims {
+				x: 0,
+				y: 0,
+				width: dims.0,
+				height: dims.1,
+			},
+		)?;
+		{
+			let sctk_window = window.window.borrow_mut();
+			sctk_window.set_decorate(false);
+			sctk_window.set_resizable(false);
+		}
+		Ok(window)
+	}
+
+	fn window_id(&self, window: &Self::Window) -> Result<Self::Id, Self::Error> {
+		Ok(window.id)
+	}
+
+	fn drain_events_immediate(&self, window: &mut Self::Window, f: &mut dyn FnMut(WindowEvent)) {
+		if let Err(e) = self.dispatch() {
+			log::error!("Failed to dispatch Wayland events: {:?}", e);
+		}
+
+		let state = self.with_window_state(window, |state| {
+			let frame_size = if state.frame_outdated {
+				state.frame_outdated = false;
+				Some(state.size)
+			} else {
+				None
+			};
+			(std::mem::take(&mut state.events), frame_size)
+		});
+		let (events, frame_size) = match state {
+			Ok(state) => state,
+			Err(e) => {
+				log::error!("Failed to get events: {}", e);
+				return;
+			}
+		};
+		if let Some((width, height)) = frame_size {
+			let sctk_window = window.window.get_mut();
+			sctk_window.resize(width, height);
+			sctk_window.refresh();
+		}
+		for evt in events {
+			f(evt);
+		}
+	}
+
+	fn wait_events_timeout(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>, deadline: Option<Instant>) {
+		let start_len = event_buf.len();
+		// winit enables loading libwayland-client at runtime, so its functions are called through the library handle
+		let fd = unsafe { (client::sys::client::WAYLAND_CLIENT_HANDLE.wl_display_get_fd)(self.display.get_display_ptr()) };
+		loop {
+			self.get_window_events(window, event_buf);
+			if event_buf.len() > start_len {
+				return;
+			}
+			// Events for other windows or the decorations also wake this up, so check again afterwards
+			if !poll_readable(fd, deadline) {
+				return;
+			}
+		}
+	}
+
+	fn set_window_size(&self, window: &Self::Window, dims: (u32, u32)) -> Result<(), Self::Error> {
+		// Wayland clients pick their own size, so this takes effect immediately instead of waiting for the compositor
+		self.with_window_state(window, |state| {
+			let scale = state.scale as u32;
+			state.size = ((dims.0 / scale).max(1), (dims.1 / scale).max(1));
+			state.frame_outdated = true;
+			let dims = state.physical_size();
+			state.events.push_back(WindowEvent::ResizeHappened {
+				dims: (dims.0 as f64, dims.1 as f64),
+			});
+		})
+	}
+
+	fn set_window_position(&self, _window: &Self::Window, _position: (i32, i32)) -> Result<(), Self::Error> {
+		Err(WaylandBackendError::Unsupported("set_window_position"))
+	}
+
+	fn set_decorations(&self, window: &Self::Window, decorated: bool) -> Result<(), Self::Error> {
+		window.window.borrow().set_decorate(decorated);
+		Ok(())
+	}
+
+	fn set_size_hints(
+		&self,
+		window: &Self::Window,
+		min: Option<(u32, u32)>,
+		max: Option<(u32, u32)>,
+		aspect: Option<(f64, f64)>,
+	) -> Result<(), Self::Error> {
+		if aspect.is_some() {
+			return Err(WaylandBackendError::Unsupported("aspect ratio size hints"));
+		}
+		let scale = self.with_window_state(window, |state| state.scale as u32)?;
+		let logical = |dims: (u32, u32)| (dims.0 / scale, dims.1 / scale);
+		let mut sctk_window = window.window.borrow_mut();
+		sctk_window.set_min_size(min.map(logical));
+		sctk_window.set_max_size(max.map(logical));
+		Ok(())
+	}
+
+	fn get_window_size(&self, window: &Self::Window) -> Result<(u32, u32), Self::Error> {
+		self.with_window_state(window, |state| state.physical_size())
+	}
+
+	fn get_window_position(&self, _window: &Self::Window) -> Result<(i32, i32), Self::Error> {
+		Err(WaylandBackendError::Unsupported("get_window_position"))
+	}
+
+	fn get_work_area(&self, _window: &Self::Window) -> Result<WindowDims, Self::Error> {
+		Err(WaylandBackendError::Unsupported("get_work_area"))
+	}
+
+	fn activate(&self, _window: &Self::Window) -> Result<(), Self::Error> {
+		Err(WaylandBackendError::Unsupported("activate"))
+	}
+
+	fn set_skip_taskbar(&self, _window: &Self::Window, _skip: bool) -> Result<(), Self::Error> {
+		Err(WaylandBackendError::Unsupported("set_skip_taskbar"))
+	}
+
+	fn set_skip_pager(&self, _window: &Self::Window, _skip: bool) -> Result<(), Self::Error> {
+		Err(WaylandBackendError::Unsupported("set_skip_pager"))
+	}
+
+	fn set_always_on_top(&self, _window: &Self::Window, _on_top: bool) -> Result<(), Self::Error> {
+		// xdg-shell leaves stacking entirely to the compositor
+		Err(WaylandBackendError::Unsupported("set_always_on_top"))
+	}
+
+	fn set_window_type(&self, _window: &Self::Window, _ty: WindowType) -> Result<(), Self::Error> {
+		Err(WaylandBackendError::Unsupported("set_window_type"))
+	}
+
+	fn set_opacity(&self, _window: &Self::Window, _opacity: f64) -> Result<(), Self::Error> {
+		// Translucency on Wayland comes from the alpha channel of the buffer, there's no per-window opacity
+		Err(WaylandBackendError::Unsupported("set_opacity"))
+	}
+
+	fn is_window_open(&self, window: &Self::Window) -> Result<bool, Self::Error> {
+		// Closing is up to the client on Wayland, so a close request is as closed as a window gets until it's dropped
+		self.with_window_state(window, |state| !state.closed)
+	}
+
+	fn set_window_title(&self, window: &Self::Window, title: &str) -> Result<(), Self::Error> {
+		window.window.borrow().set_title(title.to_owned());
+		Ok(())
+	}
+
+	fn set_fullscreen(&self, window: &Self::Window, fullscreen: bool) -> Result<(), Self::Error> {
+		let sctk_window = window.window.borrow();
+		if fullscreen {
+			sctk_window.set_fullscreen(None);
+		} else {
+			sctk_window.unset_fullscreen();
+		}
+		Ok(())
+	}
+
+	fn set_minimized(&self, window: &Self::Window, minimized: bool) -> Result<(), Self::Error> {
+		if !minimized {
+			// xdg_shell has no request to restore a minimized window, only the user can do that
+			return Err(WaylandBackendError::Unsupported("set_minimized"));
+		}
+		window.window.borrow().set_minimized();
+		Ok(())
+	}
+
+	fn set_maximized(&self, window: &Self::Window, maximized: bool) -> Result<(), Self::Error> {
+		let sctk_window = window.window.borrow();
+		if maximized {
+			sctk_window.set_maximized();
+		} else {
+			sctk_window.unset_maximized();
+		}
+		Ok(())
+	}
+
+	fn get_scale_factor(&self, window: &Self::Window) -> Result<f64, Self::Error> {
+		self.with_window_state(window, |state| state.scale as f64)
+	}
+
+	fn present(&self) {
+		{
+			let shared = self.shared.lock().unwrap();
+			for (id, target) in self.targets.borrow_mut().iter_mut() {
+				let state = &shared.windows[id];
+				if !state.configured {
+					continue;
+				}
+				if let Err(e) = target.present(state) {
+					log::error!("Failed to present window: {:?}", e);
+				}
+			}
+		}
+		if let Err(e) = self.display.flush() {
+			log::error!("Failed to flush the Wayland connection: {}", e);
+		}
+	}
+
+	fn close(&self, window: Self::Window) {
+		self.targets.borrow_mut().remove(&window.id);
+		let mut shared = self.shared.lock().unwrap();
+		shared.windows.remove(&window.id);
+		if shared.pointer_focus == Some(window.id) {
+			shared.pointer_focus = None;
+		}
+		if shared.keyboard_focus == Some(window.id) {
+			shared.keyboard_focus = None;
+		}
+		drop(shared);
+		drop(window);
+		if let Err(e) = self.display.flush() {
+			log::error!("Failed to flush the Wayland connection: {}", e);
+		}
+	}
+}
+
+impl SurfaceCreator<Self, CairoBackend> for WaylandBackend {
+	type Error = WaylandBackendError;
+
+	/// Creates an image surface for the window to draw into. Image surfaces can't be resized, so it's made as large as the
+	/// biggest output to leave room for the window to grow, and `present` only sends the part the window covers.
+	fn create_surface(&self, args: &WaylandWindow) -> Result<CairoSurface, WaylandBackendError> {
+		let window_size = self.get_window_size(args)?;
+		let (width, height) = self.env.outputs.with_all(|outputs| {
+			outputs
+				.iter()
+				.flat_map(|(_, _, info)| info.modes.iter().filter(|mode| mode.is_current))
+				.fold((window_size.0 as i32, window_size.1 as i32), |(width, height), mode| {
+					(width.max(mode.dimensions.0), height.max(mode.dimensions.1))
+				})
+		});
+		log::trace!("Creating image surface with dims {}x{}", width, height);
+		let surface =
+			cairo::ImageSurface::create(cairo::Format::ARgb32, width, height).map_err(WaylandBackendError::SurfaceCreation)?;
+		if let Some(target) = self.targets.borrow_mut().get_mut(&args.id) {
+			target.image = Some(surface.clone());
+		}
+		Ok(CairoSurface::from_surface((*surface).clone()))
+	}
+}
+
+#[derive(Debug)]
+pub enum WaylandBackendError {
+	ConnectError(ConnectError),
+	IoError(std::io::Error),
+	Unsupported(&'static str),
+	/// Cairo couldn't create the surface a window is drawn into.
+	SurfaceCreation(cairo::Status),
+	/// A window was passed to a different backend than the one that created it.
+	WindowMismatch,
+}
+
+impl std::fmt::Display for WaylandBackendError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			WaylandBackendError::ConnectError(_) => write!(f, "failed to connect to the Wayland compositor"),
+			WaylandBackendError::IoError(e) => write!(f, "{}", e),
+			WaylandBackendError::Unsupported(operation) => write!(f, "{} is not supported by the Wayland backend", operation),
+			WaylandBackendError::SurfaceCreation(status) => write!(f, "failed to create a Cairo surface: {}", status),
+			WaylandBackendError::WindowMismatch => write!(f, "window belongs to a different backend"),
+		}
+	}
+}
+
+impl std::error::Error for WaylandBackendError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			WaylandBackendError::ConnectError(e) => Some(e),
+			WaylandBackendError::IoError(e) => e.source(),
+			_ => None,
+		}
+	}
+}
@@ -1,25 +1,63 @@
-use crate::window::{WindowBackend, WindowDims, WindowEvent};
+use crate::window::{WindowBackend, WindowDims, WindowEvent, WindowType};
 
 use crate::drawing::cairo::CairoBackend;
 use crate::drawing::cairo::CairoSurface;
 use crate::drawing::SurfaceCreator;
+use crate::event::Key;
+use crate::event::KeyboardEvent;
+use crate::event::Modifiers;
 use crate::event::MouseButton;
 use crate::event::MouseClickEvent;
 use crate::event::MouseMoveEvent;
 use crate::event::PressState;
+use crate::event::ScrollEvent;
+use crate::event::ScrollUnit;
 use std::collections::VecDeque;
+use std::time::Instant;
 use winit::{Event, EventsLoop, Window};
 
 pub struct WinitWindow {
 	window: Window,
 	events_loop: EventsLoop,
 	last_cursor_position: (f64, f64),
+	last_modifiers: Modifiers,
+	closed: bool,
 }
 
 pub struct WinitBackend;
 
+impl WinitBackend {
+	/// Translates a point relative to the window's client area into screen coordinates.
+	pub fn window_to_root(&self, window: &WinitWindow, x: f64, y: f64) -> Result<(i32, i32), WinitBackendError> {
+		let origin = self.get_inner_position(window)?;
+		Ok((origin.0 + x.round() as i32, origin.1 + y.round() as i32))
+	}
+
+	/// Translates a point in screen coordinates into coordinates relative to the window's client area.
+	pub fn root_to_window(&self, window: &WinitWindow, x: f64, y: f64) -> Result<(i32, i32), WinitBackendError> {
+		let origin = self.get_inner_position(window)?;
+		Ok((x.round() as i32 - origin.0, y.round() as i32 - origin.1))
+	}
+
+	/// Gets the modifier keys that were held during the last input event. winit has no way to query the current state
+	/// directly, and doesn't report lock keys.
+	pub fn query_modifiers(&self, window: &WinitWindow) -> Modifiers {
+		window.last_modifiers
+	}
+
+	fn get_inner_position(&self, window: &WinitWindow) -> Result<(i32, i32), WinitBackendError> {
+		let physical = window
+			.window
+			.get_inner_position()
+			.ok_or(WinitBackendError::Unknown)?
+			.to_physical(window.window.get_hidpi_factor());
+		Ok((physical.x.round() as i32, physical.y.round() as i32))
+	}
+}
+
 impl WindowBackend for WinitBackend {
 	type Window = WinitWindow;
+	type Id = winit::WindowId;
 	type Error = WinitBackendError;
 
 	fn init() -> Result<Self, Self::Error> {
@@ -28,59 +66,278 @@ impl WindowBackend for WinitBackend {
 
 	fn create_window(&self, title: &str, dims: WindowDims) -> Result<Self::Window, Self::Error> {
 		let events_loop = EventsLoop::new();
+		// The window isn't on a monitor yet, so size it for the primary one
+		let hidpi_factor = events_loop.get_primary_monitor().get_hidpi_factor();
 
 		let window = winit::WindowBuilder::new()
 			.with_title(title)
+			.with_dimensions(winit::dpi::LogicalSize::from_physical(
+				(dims.width, dims.height),
+				hidpi_factor,
+			))
 			.build(&events_loop)
 			.map_err(WinitBackendError::CreationError)?;
+		// The builder can't take a position, so move the window right away. Some window managers ignore initial
+		// position requests and place new windows wherever they like.
+		window.set_position(winit::dpi::LogicalPosition::from_physical(
+			(dims.x, dims.y),
+			window.get_hidpi_factor(),
+		));
 
 		Ok(WinitWindow {
 			window,
 			events_loop,
 			last_cursor_position: (0.0, 0.0),
+			last_modifiers: Modifiers::default(),
+			closed: false,
 		})
 	}
 
-	fn get_window_events(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>) {
+	fn create_splash_window(&self, title: &str, dims: (u32, u32)) -> Result<Self::Window, Self::Error> {
+		use winit::os::unix::{WindowBuilderExt, XWindowType};
+
+		let events_loop = EventsLoop::new();
+		let monitor = events_loop.get_primary_monitor();
+		let monitor_position = monitor.get_position();
+		let monitor_dims = monitor.get_dimensions();
+
+		let window = winit::WindowBuilder::new()
+			.with_title(title)
+			.with_dimensions(winit::dpi::LogicalSize::from_physical(dims, monitor.get_hidpi_factor()))
+			.with_decorations(false)
+			.with_always_on_top(true)
+			.with_x11_window_type(XWindowType::Splash)
+			.build(&events_loop)
+			.map_err(WinitBackendError::CreationError)?;
+		window.set_position(winit::dpi::LogicalPosition::from_physical(
+			(
+				monitor_position.x + (monitor_dims.width - dims.0 as f64) / 2.0,
+				monitor_position.y + (monitor_dims.height - dims.1 as f64) / 2.0,
+			),
+			window.get_hidpi_factor(),
+		));
+
+		Ok(WinitWindow {
+			window,
+			events_loop,
+			last_cursor_position: (0.0, 0.0),
+			last_modifiers: Modifiers::default(),
+			closed: false,
+		})
+	}
+
+	fn window_id(&self, window: &Self::Window) -> Result<Self::Id, Self::Error> {
+		Ok(window.window.id())
+	}
+
+	fn drain_events_immediate(&self, window: &mut Self::Window, f: &mut dyn FnMut(WindowEvent)) {
 		let events_loop = &mut window.events_loop;
 		let last_cursor_position = &mut window.last_cursor_position;
+		let last_modifiers = &mut window.last_modifiers;
+		let closed = &mut window.closed;
+		let mut hidpi_factor = window.window.get_hidpi_factor();
 		events_loop.poll_events(|evt| {
-			if let Some(mut evt) = convert_winit_event(evt) {
-				// Necessary because winit mouse click events don't contain the position of the click
-				match evt {
-					WindowEvent::MouseMove(ref mut mouse_move_event) => {
-						*last_cursor_position = mouse_move_event.pos;
+			if let Some(evt) = process_winit_event(evt, &mut hidpi_factor, last_cursor_position, last_modifiers, closed) {
+				f(evt);
+			}
+		});
+	}
+
+	fn wait_events_timeout(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>, deadline: Option<Instant>) {
+		{
+			let events_loop = &mut window.events_loop;
+			let last_cursor_position = &mut window.last_cursor_position;
+			let last_modifiers = &mut window.last_modifiers;
+			let closed = &mut window.closed;
+			let mut hidpi_factor = window.window.get_hidpi_factor();
+			if let Some(deadline) = deadline {
+				// winit 0.18 can't wait with a timeout, so wake the loop up from another thread once the deadline passes
+				let proxy = events_loop.create_proxy();
+				std::thread::spawn(move || {
+					if let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+						std::thread::sleep(remaining);
 					}
-					WindowEvent::MouseClick(ref mut mouse_click_event) => {
-						mouse_click_event.pos = *last_cursor_position;
+					let _ = proxy.wakeup();
+				});
+			}
+			// Keep waiting through events that don't translate to anything, like device events
+			events_loop.run_forever(|evt| {
+				if let Event::Awakened = evt {
+					// Wakeups left over from earlier waits arrive too, so only stop once this wait's deadline passed
+					return match deadline {
+						Some(deadline) if Instant::now() >= deadline => winit::ControlFlow::Break,
+						_ => winit::ControlFlow::Continue,
+					};
+				}
+				match process_winit_event(evt, &mut hidpi_factor, last_cursor_position, last_modifiers, closed) {
+					Some(evt) => {
+						event_buf.push_back(evt);
+						winit::ControlFlow::Break
 					}
-					_ => {}
+					None => winit::ControlFlow::Continue,
 				}
-
-				event_buf.push_back(evt);
-			}
-		});
+			});
+		}
+		self.get_window_events(window, event_buf);
 	}
 
-	fn set_window_size(&self, window: &Self::Window, dims: (u32, u32)) {
-		unimplemented!()
+	fn set_window_size(&self, window: &Self::Window, dims: (u32, u32)) -> Result<(), Self::Error> {
+		let dims = (dims.0.max(1), dims.1.max(1));
+		let hidpi_factor = window.window.get_hidpi_factor();
+		window
+			.window
+			.set_inner_size(winit::dpi::LogicalSize::from_physical(dims, hidpi_factor));
+		Ok(())
 	}
 
 	fn set_window_position(&self, window: &Self::Window, position: (i32, i32)) -> Result<(), Self::Error> {
 		window.window.set_position(winit::dpi::LogicalPosition::from_physical(
 			(position.0 as i32, position.1 as i32),
-			1.0,
+			window.window.get_hidpi_factor(),
 		));
 		Ok(())
 	}
 
+	fn set_decorations(&self, window: &Self::Window, decorated: bool) -> Result<(), Self::Error> {
+		window.window.set_decorations(decorated);
+		Ok(())
+	}
+
+	fn set_size_hints(
+		&self,
+		window: &Self::Window,
+		min: Option<(u32, u32)>,
+		max: Option<(u32, u32)>,
+		aspect: Option<(f64, f64)>,
+	) -> Result<(), Self::Error> {
+		if aspect.is_some() {
+			return Err(WinitBackendError::Unsupported("aspect ratio size hints"));
+		}
+		let hidpi_factor = window.window.get_hidpi_factor();
+		let logical = |dims: (u32, u32)| winit::dpi::LogicalSize::from_physical(dims, hidpi_factor);
+		window.window.set_min_dimensions(min.map(logical));
+		window.window.set_max_dimensions(max.map(logical));
+		Ok(())
+	}
+
 	fn get_window_size(&self, window: &Self::Window) -> Result<(u32, u32), Self::Error> {
-		let physical = window.window.get_inner_size().unwrap().to_physical(1.0);
+		let physical = window
+			.window
+			.get_inner_size()
+			.ok_or(WinitBackendError::Unknown)?
+			.to_physical(window.window.get_hidpi_factor());
 		Ok((physical.width.round() as u32, physical.height.round() as u32))
 	}
 
-	fn is_window_open(&self, window: &Self::Window) {
-		unimplemented!()
+	fn get_window_position(&self, window: &Self::Window) -> Result<(i32, i32), Self::Error> {
+		// get_position is the position of the decorations, use the client area to agree with the XCB backend
+		self.get_inner_position(window)
+	}
+
+	fn move_by(&self, window: &Self::Window, dx: i32, dy: i32) -> Result<(), Self::Error> {
+		// set_position moves the decorations, so offset from their position to avoid drifting by the decoration size
+		let physical = window
+			.window
+			.get_position()
+			.ok_or(WinitBackendError::Unknown)?
+			.to_physical(window.window.get_hidpi_factor());
+		self.set_window_position(window, (physical.x.round() as i32 + dx, physical.y.round() as i32 + dy))
+	}
+
+	fn get_work_area(&self, window: &Self::Window) -> Result<WindowDims, Self::Error> {
+		// winit doesn't expose the work area, so use the whole monitor
+		let monitor = window.window.get_current_monitor();
+		let position = monitor.get_position();
+		let dims = monitor.get_dimensions();
+		Ok(WindowDims {
+			x: position.x.round() as i32,
+			y: position.y.round() as i32,
+			width: dims.width.round() as u32,
+			height: dims.height.round() as u32,
+		})
+	}
+
+	fn activate(&self, _window: &Self::Window) -> Result<(), Self::Error> {
+		// winit 0.18 has no way to request focus or attention
+		Err(WinitBackendError::Unsupported("activate"))
+	}
+
+	fn set_skip_taskbar(&self, _window: &Self::Window, _skip: bool) -> Result<(), Self::Error> {
+		Err(WinitBackendError::Unsupported("set_skip_taskbar"))
+	}
+
+	fn set_skip_pager(&self, _window: &Self::Window, _skip: bool) -> Result<(), Self::Error> {
+		Err(WinitBackendError::Unsupported("set_skip_pager"))
+	}
+
+	fn set_always_on_top(&self, window: &Self::Window, on_top: bool) -> Result<(), Self::Error> {
+		window.window.set_always_on_top(on_top);
+		Ok(())
+	}
+
+	fn set_window_type(&self, _window: &Self::Window, _ty: WindowType) -> Result<(), Self::Error> {
+		Err(WinitBackendError::Unsupported("set_window_type"))
+	}
+
+	fn set_opacity(&self, _window: &Self::Window, _opacity: f64) -> Result<(), Self::Error> {
+		// winit 0.18 can only make windows transparent when they're created
+		Err(WinitBackendError::Unsupported("set_opacity"))
+	}
+
+	fn is_window_open(&self, window: &Self::Window) -> Result<bool, Self::Error> {
+		// winit windows stay alive until they're dropped, so a close request is the best indication there is
+		Ok(!window.closed)
+	}
+
+	fn set_window_title(&self, window: &Self::Window, title: &str) -> Result<(), Self::Error> {
+		window.window.set_title(title);
+		Ok(())
+	}
+
+	fn set_fullscreen(&self, window: &Self::Window, fullscreen: bool) -> Result<(), Self::Error> {
+		let monitor = if fullscreen {
+			Some(window.window.get_current_monitor())
+		} else {
+			None
+		};
+		window.window.set_fullscreen(monitor);
+		Ok(())
+	}
+
+	fn set_minimized(&self, window: &Self::Window, minimized: bool) -> Result<(), Self::Error> {
+		use winit::os::unix::WindowExt;
+
+		if !minimized {
+			window.window.show();
+			return Ok(());
+		}
+		// winit 0.18 can't iconify windows, so go through Xlib directly
+		let x_window = window
+			.window
+			.get_xlib_window()
+			.ok_or(WinitBackendError::Unsupported("set_minimized"))?;
+		let x_dpy = window
+			.window
+			.get_xlib_display()
+			.ok_or(WinitBackendError::Unsupported("set_minimized"))?;
+		let x_screen = window
+			.window
+			.get_xlib_screen_id()
+			.ok_or(WinitBackendError::Unsupported("set_minimized"))?;
+		let status = unsafe { x11::xlib::XIconifyWindow(x_dpy as *mut _, x_window, x_screen) };
+		if status == 0 {
+			return Err(WinitBackendError::Unknown);
+		}
+		Ok(())
+	}
+
+	fn set_maximized(&self, window: &Self::Window, maximized: bool) -> Result<(), Self::Error> {
+		window.window.set_maximized(maximized);
+		Ok(())
+	}
+
+	fn get_scale_factor(&self, window: &Self::Window) -> Result<f64, Self::Error> {
+		Ok(window.window.get_hidpi_factor())
 	}
 
 	fn present(&self) {}
@@ -90,16 +347,88 @@ impl WindowBackend for WinitBackend {
 	}
 }
 
+#[cfg(feature = "display-tests")]
+#[test]
+fn set_window_size_round_trips() {
+	let backend = WinitBackend::init().unwrap();
+	let mut window = backend
+		.create_window(
+			"set_window_size_round_trips",
+			WindowDims {
+				x: 0,
+				y: 0,
+				width: 200,
+				height: 100,
+			},
+		)
+		.unwrap();
+	backend.set_window_size(&window, (300, 150)).unwrap();
+
+	// The resize is applied asynchronously
+	let mut size = (0, 0);
+	for _ in 0..50 {
+		backend.drain_events_immediate(&mut window, &mut |_| {});
+		size = backend.get_window_size(&window).unwrap();
+		if size == (300, 150) {
+			break;
+		}
+		std::thread::sleep(std::time::Duration::from_millis(10));
+	}
+	assert!(
+		(size.0 as i32 - 300).abs() <= 1 && (size.1 as i32 - 150).abs() <= 1,
+		"got {:?}",
+		size
+	);
+}
+
+#[cfg(feature = "raw-window-handle")]
+unsafe impl raw_window_handle::HasRawWindowHandle for WinitWindow {
+	fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+		use winit::os::unix::WindowExt;
+
+		if let (Some(surface), Some(display)) = (self.window.get_wayland_surface(), self.window.get_wayland_display()) {
+			let mut handle = raw_window_handle::WaylandHandle::empty();
+			handle.surface = surface;
+			handle.display = display;
+			return raw_window_handle::RawWindowHandle::Wayland(handle);
+		}
+
+		let mut handle = raw_window_handle::XlibHandle::empty();
+		if let (Some(window), Some(display)) = (self.window.get_xlib_window(), self.window.get_xlib_display()) {
+			handle.window = window;
+			handle.display = display;
+			unsafe {
+				let mut attributes = std::mem::zeroed();
+				if x11::xlib::XGetWindowAttributes(display as *mut _, window, &mut attributes) != 0 {
+					handle.visual_id = x11::xlib::XVisualIDFromVisual(attributes.visual);
+				}
+			}
+		}
+		raw_window_handle::RawWindowHandle::Xlib(handle)
+	}
+}
+
 impl SurfaceCreator<Self, CairoBackend> for WinitBackend {
+	type Error = WinitBackendError;
+
 	//TODO: make cross platform
-	fn create_surface(&self, args: &WinitWindow) -> CairoSurface {
+	fn create_surface(&self, args: &WinitWindow) -> Result<CairoSurface, WinitBackendError> {
 		use winit::os::unix::WindowExt;
 
 		let window = &args.window;
-		let dims = window.get_inner_size().unwrap().to_physical(1.0);
-		let x_window = window.get_xlib_window().unwrap();
-		let x_dpy = window.get_xlib_display().unwrap();
-		let x_screen = window.get_xlib_screen_id().unwrap();
+		// The inner size is only missing once the window is gone
+		let dims = window
+			.get_inner_size()
+			.ok_or(WinitBackendError::Unknown)?
+			.to_physical(window.get_hidpi_factor());
+		let (x_window, x_dpy, x_screen) = match (
+			window.get_xlib_window(),
+			window.get_xlib_display(),
+			window.get_xlib_screen_id(),
+		) {
+			(Some(x_window), Some(x_dpy), Some(x_screen)) => (x_window, x_dpy, x_screen),
+			_ => return Err(WinitBackendError::Unsupported("create_surface on a non-X11 window")),
+		};
 
 		let surface = unsafe {
 			cairo_sys::cairo_xlib_surface_create(
@@ -111,20 +440,197 @@ impl SurfaceCreator<Self, CairoBackend> for WinitBackend {
 			)
 		};
 
-		unsafe { CairoSurface::from_surface(cairo::Surface::from_raw_full(surface)) }
+		unsafe { Ok(CairoSurface::from_surface(cairo::Surface::from_raw_full(surface))) }
+	}
+}
+
+impl From<winit::VirtualKeyCode> for Key {
+	fn from(keycode: winit::VirtualKeyCode) -> Self {
+		use winit::VirtualKeyCode;
+
+		match keycode {
+			VirtualKeyCode::A => Key::A,
+			VirtualKeyCode::B => Key::B,
+			VirtualKeyCode::C => Key::C,
+			VirtualKeyCode::D => Key::D,
+			VirtualKeyCode::E => Key::E,
+			VirtualKeyCode::F => Key::F,
+			VirtualKeyCode::G => Key::G,
+			VirtualKeyCode::H => Key::H,
+			VirtualKeyCode::I => Key::I,
+			VirtualKeyCode::J => Key::J,
+			VirtualKeyCode::K => Key::K,
+			VirtualKeyCode::L => Key::L,
+			VirtualKeyCode::M => Key::M,
+			VirtualKeyCode::N => Key::N,
+			VirtualKeyCode::O => Key::O,
+			VirtualKeyCode::P => Key::P,
+			VirtualKeyCode::Q => Key::Q,
+			VirtualKeyCode::R => Key::R,
+			VirtualKeyCode::S => Key::S,
+			VirtualKeyCode::T => Key::T,
+			VirtualKeyCode::U => Key::U,
+			VirtualKeyCode::V => Key::V,
+			VirtualKeyCode::W => Key::W,
+			VirtualKeyCode::X => Key::X,
+			VirtualKeyCode::Y => Key::Y,
+			VirtualKeyCode::Z => Key::Z,
+			VirtualKeyCode::Key0 => Key::Key0,
+			VirtualKeyCode::Key1 => Key::Key1,
+			VirtualKeyCode::Key2 => Key::Key2,
+			VirtualKeyCode::Key3 => Key::Key3,
+			VirtualKeyCode::Key4 => Key::Key4,
+			VirtualKeyCode::Key5 => Key::Key5,
+			VirtualKeyCode::Key6 => Key::Key6,
+			VirtualKeyCode::Key7 => Key::Key7,
+			VirtualKeyCode::Key8 => Key::Key8,
+			VirtualKeyCode::Key9 => Key::Key9,
+			VirtualKeyCode::Numpad0 => Key::Numpad0,
+			VirtualKeyCode::Numpad1 => Key::Numpad1,
+			VirtualKeyCode::Numpad2 => Key::Numpad2,
+			VirtualKeyCode::Numpad3 => Key::Numpad3,
+			VirtualKeyCode::Numpad4 => Key::Numpad4,
+			VirtualKeyCode::Numpad5 => Key::Numpad5,
+			VirtualKeyCode::Numpad6 => Key::Numpad6,
+			VirtualKeyCode::Numpad7 => Key::Numpad7,
+			VirtualKeyCode::Numpad8 => Key::Numpad8,
+			VirtualKeyCode::Numpad9 => Key::Numpad9,
+			VirtualKeyCode::F1 => Key::F1,
+			VirtualKeyCode::F2 => Key::F2,
+			VirtualKeyCode::F3 => Key::F3,
+			VirtualKeyCode::F4 => Key::F4,
+			VirtualKeyCode::F5 => Key::F5,
+			VirtualKeyCode::F6 => Key::F6,
+			VirtualKeyCode::F7 => Key::F7,
+			VirtualKeyCode::F8 => Key::F8,
+			VirtualKeyCode::F9 => Key::F9,
+			VirtualKeyCode::F10 => Key::F10,
+			VirtualKeyCode::F11 => Key::F11,
+			VirtualKeyCode::F12 => Key::F12,
+			VirtualKeyCode::Escape => Key::Escape,
+			VirtualKeyCode::Space => Key::Space,
+			VirtualKeyCode::Tab => Key::Tab,
+			VirtualKeyCode::Delete => Key::Delete,
+			VirtualKeyCode::Insert => Key::Insert,
+			VirtualKeyCode::Home => Key::Home,
+			VirtualKeyCode::End => Key::End,
+			VirtualKeyCode::PageUp => Key::PageUp,
+			VirtualKeyCode::PageDown => Key::PageDown,
+			VirtualKeyCode::Left => Key::Left,
+			VirtualKeyCode::Right => Key::Right,
+			VirtualKeyCode::Up => Key::Up,
+			VirtualKeyCode::Down => Key::Down,
+			VirtualKeyCode::Minus => Key::Minus,
+			VirtualKeyCode::Equals => Key::Equals,
+			VirtualKeyCode::Comma => Key::Comma,
+			VirtualKeyCode::Period => Key::Period,
+			VirtualKeyCode::Slash => Key::Slash,
+			VirtualKeyCode::Backslash => Key::Backslash,
+			VirtualKeyCode::Semicolon => Key::Semicolon,
+			VirtualKeyCode::Apostrophe => Key::Apostrophe,
+			VirtualKeyCode::Grave => Key::Grave,
+			VirtualKeyCode::LBracket => Key::LBracket,
+			VirtualKeyCode::RBracket => Key::RBracket,
+			VirtualKeyCode::LShift => Key::LShift,
+			VirtualKeyCode::RShift => Key::RShift,
+			VirtualKeyCode::LControl => Key::LControl,
+			VirtualKeyCode::RControl => Key::RControl,
+			VirtualKeyCode::LAlt => Key::LAlt,
+			VirtualKeyCode::RAlt => Key::RAlt,
+			VirtualKeyCode::Return => Key::Enter,
+			VirtualKeyCode::NumpadEnter => Key::Enter,
+			VirtualKeyCode::Back => Key::Backspace,
+			VirtualKeyCode::LWin => Key::LLogo,
+			VirtualKeyCode::RWin => Key::RLogo,
+			VirtualKeyCode::Capital => Key::CapsLock,
+			VirtualKeyCode::Numlock => Key::NumLock,
+			_ => Key::Unknown,
+		}
+	}
+}
+
+/// Translates a winit event and keeps the window's input state up to date with it.
+fn process_winit_event(
+	evt: winit::Event,
+	hidpi_factor: &mut f64,
+	last_cursor_position: &mut (f64, f64),
+	last_modifiers: &mut Modifiers,
+	closed: &mut bool,
+) -> Option<WindowEvent> {
+	match evt {
+		Event::WindowEvent {
+			event: winit::WindowEvent::CloseRequested,
+			..
+		}
+		| Event::WindowEvent {
+			event: winit::WindowEvent::Destroyed,
+			..
+		} => *closed = true,
+		_ => {}
+	}
+	if let Some(modifiers) = winit_event_modifiers(&evt) {
+		*last_modifiers = modifiers;
+	}
+	let mut evt = convert_winit_event(evt, *hidpi_factor)?;
+	// Necessary because winit mouse click events don't contain the position of the click
+	match evt {
+		WindowEvent::MouseMove(ref mut mouse_move_event) => {
+			*last_cursor_position = mouse_move_event.pos;
+		}
+		WindowEvent::MouseClick(ref mut mouse_click_event) => {
+			mouse_click_event.pos = *last_cursor_position;
+		}
+		WindowEvent::Scroll(ref mut scroll_event) => {
+			scroll_event.pos = *last_cursor_position;
+		}
+		WindowEvent::ScaleFactorChanged { scale } => {
+			*hidpi_factor = scale;
+		}
+		_ => {}
 	}
+	Some(evt)
 }
 
-fn convert_winit_event(evt: winit::Event) -> Option<WindowEvent> {
+fn winit_event_modifiers(evt: &winit::Event) -> Option<Modifiers> {
+	let modifiers = match evt {
+		Event::WindowEvent { event, .. } => match event {
+			winit::WindowEvent::CursorMoved { modifiers, .. } => modifiers,
+			winit::WindowEvent::MouseInput { modifiers, .. } => modifiers,
+			winit::WindowEvent::MouseWheel { modifiers, .. } => modifiers,
+			winit::WindowEvent::KeyboardInput { input, .. } => &input.modifiers,
+			_ => return None,
+		},
+		_ => return None,
+	};
+	Some(convert_winit_modifiers(*modifiers))
+}
+
+fn convert_winit_modifiers(modifiers: winit::ModifiersState) -> Modifiers {
+	Modifiers {
+		shift: modifiers.shift,
+		ctrl: modifiers.ctrl,
+		alt: modifiers.alt,
+		logo: modifiers.logo,
+		caps_lock: false,
+		num_lock: false,
+	}
+}
+
+fn convert_winit_event(evt: winit::Event, hidpi_factor: f64) -> Option<WindowEvent> {
 	Some(match evt {
 		Event::WindowEvent { event, .. } => match event {
 			winit::WindowEvent::CloseRequested => WindowEvent::CloseRequested,
 			winit::WindowEvent::Resized(logical_size) => {
-				let physical = logical_size.to_physical(1.0);
+				let physical = logical_size.to_physical(hidpi_factor);
 				WindowEvent::ResizeHappened {
 					dims: (physical.width, physical.height),
 				}
 			}
+			winit::WindowEvent::CursorEntered { .. } => WindowEvent::MouseEnter,
+			winit::WindowEvent::CursorLeft { .. } => WindowEvent::MouseExit,
+			winit::WindowEvent::HiDpiFactorChanged(scale) => WindowEvent::ScaleFactorChanged { scale },
+			winit::WindowEvent::Focused(true) => WindowEvent::FocusGained,
+			winit::WindowEvent::Focused(false) => WindowEvent::FocusLost,
 			winit::WindowEvent::MouseInput {
 				device_id: _,
 				state,
@@ -139,18 +645,44 @@ fn convert_winit_event(evt: winit::Event) -> Option<WindowEvent> {
 					winit::MouseButton::Left => MouseButton::Left,
 					winit::MouseButton::Right => MouseButton::Right,
 					winit::MouseButton::Middle => MouseButton::Middle,
-					winit::MouseButton::Other(_) => return None,
+					winit::MouseButton::Other(button) => MouseButton::Other(button),
 				},
 				pos: (0.0, 0.0),
+				stylus: None,
+				modifiers: convert_winit_modifiers(modifiers),
+			}),
+			winit::WindowEvent::MouseWheel { delta, .. } => {
+				let (delta, unit) = match delta {
+					winit::MouseScrollDelta::LineDelta(x, y) => ((x as f64, y as f64), ScrollUnit::Lines),
+					winit::MouseScrollDelta::PixelDelta(position) => {
+						let physical = position.to_physical(hidpi_factor);
+						((physical.x, physical.y), ScrollUnit::Pixels)
+					}
+				};
+				WindowEvent::Scroll(ScrollEvent {
+					delta,
+					unit,
+					pos: (0.0, 0.0),
+				})
+			}
+			winit::WindowEvent::KeyboardInput { input, .. } => WindowEvent::Keyboard(KeyboardEvent {
+				state: match input.state {
+					winit::ElementState::Pressed => PressState::Pressed,
+					winit::ElementState::Released => PressState::Released,
+				},
+				key: input.virtual_keycode.map(Key::from).unwrap_or(Key::Unknown),
+				scancode: input.scancode,
+				modifiers: convert_winit_modifiers(input.modifiers),
 			}),
 			winit::WindowEvent::CursorMoved {
 				device_id: _,
 				position,
 				modifiers: _,
 			} => {
-				let physical = position.to_physical(1.0);
+				let physical = position.to_physical(hidpi_factor);
 				WindowEvent::MouseMove(MouseMoveEvent {
 					pos: (physical.x, physical.y),
+					stylus: None,
 				})
 			}
 			evt => {
@@ -168,5 +700,25 @@ fn convert_winit_event(evt: winit::Event) -> Option<WindowEvent> {
 #[derive(Debug)]
 pub enum WinitBackendError {
 	CreationError(winit::CreationError),
+	Unsupported(&'static str),
 	Unknown,
 }
+
+impl std::fmt::Display for WinitBackendError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			WinitBackendError::CreationError(_) => write!(f, "failed to create a window"),
+			WinitBackendError::Unsupported(operation) => write!(f, "{} is not supported by the winit backend", operation),
+			WinitBackendError::Unknown => write!(f, "unknown winit backend error"),
+		}
+	}
+}
+
+impl std::error::Error for WinitBackendError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			WinitBackendError::CreationError(e) => Some(e),
+			_ => None,
+		}
+	}
+}
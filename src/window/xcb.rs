@@ -1,28 +1,106 @@
 use crate::drawing::cairo::CairoBackend;
 use crate::drawing::cairo::CairoSurface;
-use crate::drawing::{DrawingBackend, SurfaceCreator};
+use crate::drawing::AlphaMode;
+use crate::drawing::Rect;
+use crate::drawing::SurfaceCreator;
+use crate::event::keysym::keysym_to_key;
+use crate::event::Key;
+use crate::event::KeyboardEvent;
+use crate::event::LockState;
+use crate::event::Modifiers;
 use crate::event::MouseButton;
 use crate::event::MouseClickEvent;
 use crate::event::MouseMoveEvent;
 use crate::event::PressState;
+use crate::event::ScrollEvent;
+use crate::event::ScrollUnit;
+use crate::event::StylusState;
 use crate::window::xcb::config::*;
+use crate::window::xcb::keysym::KeyboardMapping;
 use crate::window::xcb::property::*;
-use crate::window::{WindowBackend, WindowDims, WindowEvent};
+use crate::window::{poll_readable, WindowBackend, WindowDims, WindowEvent, WindowType};
 
-use std::collections::VecDeque;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Instant;
 
 pub mod config;
+mod dbe;
+mod ext;
+mod keysym;
 pub mod property;
+mod selection;
+mod xinput;
 
 pub struct XcbBackend {
 	conn: Arc<xcb::Connection>,
 	screen: xcb::Screen<'static>,
 	wm_delete_window_atom: xcb::Atom,
+	wm_take_focus_atom: xcb::Atom,
 	visual_type: xcb::Visualtype,
+	visual_types: RefCell<HashMap<xcb::Visualid, xcb::Visualtype>>,
+	xfixes_first_event: Cell<Option<u8>>,
+	keyboard_mapping: RefCell<Option<KeyboardMapping>>,
+	selection_window: Cell<Option<xcb::Window>>,
+	clipboard_text: RefCell<Option<String>>,
+	/// Events read while waiting for a specific reply event, to be handled by the next poll.
+	deferred_events: RefCell<VecDeque<xcb::GenericEvent>>,
+	double_buffering: Cell<bool>,
+	/// The DBE back buffers of double-buffered windows, by window.
+	back_buffers: RefCell<HashMap<xcb::Window, xcb::Drawable>>,
+	stylus: RefCell<xinput::StylusTracker>,
+	/// The server time of the last key, button or motion event, or `CURRENT_TIME` before the first one.
+	last_input_time: Cell<xcb::Timestamp>,
 }
 
 impl XcbBackend {
+	/// Connects to the X server on the given display, or the one named by `$DISPLAY` if `display` is `None`. Returns the
+	/// backend along with the index of the selected screen.
+	pub fn init_with_display(display: Option<&str>) -> Result<(Self, i32), XcbBackendError> {
+		let (conn, screen_idx) = xcb::Connection::connect(display).map_err(|_| XcbBackendError::ConnectionFailed)?;
+		let screen: xcb::Screen<'static> =
+			unsafe { std::mem::transmute(conn.get_setup().roots().nth(screen_idx as usize).unwrap()) };
+		// Atom referring to string "WM_DELETE_WINDOW"
+		let wm_delete_window_atom: xcb::Atom = xcb::intern_atom(&conn, false, "WM_DELETE_WINDOW").get_reply().unwrap().atom();
+		// Atom referring to string "WM_TAKE_FOCUS"
+		let wm_take_focus_atom: xcb::Atom = xcb::intern_atom(&conn, false, "WM_TAKE_FOCUS").get_reply().unwrap().atom();
+
+		let mut visual_type = None;
+		'outer: for depth in screen.allowed_depths() {
+			if depth.depth() != 32 {
+				continue;
+			}
+
+			for test_visual_type in depth.visuals() {
+				visual_type = Some(test_visual_type);
+				break 'outer;
+			}
+		}
+		let visual_type = visual_type.unwrap();
+
+		let backend = Self {
+			conn: Arc::new(conn),
+			screen,
+			wm_delete_window_atom,
+			wm_take_focus_atom,
+			visual_type,
+			visual_types: RefCell::new(HashMap::new()),
+			xfixes_first_event: Cell::new(None),
+			keyboard_mapping: RefCell::new(None),
+			selection_window: Cell::new(None),
+			clipboard_text: RefCell::new(None),
+			deferred_events: RefCell::new(VecDeque::new()),
+			double_buffering: Cell::new(false),
+			back_buffers: RefCell::new(HashMap::new()),
+			stylus: RefCell::new(xinput::StylusTracker::default()),
+			last_input_time: Cell::new(xcb::CURRENT_TIME),
+		};
+		backend.select_stylus_events();
+		Ok((backend, screen_idx))
+	}
+
 	pub fn get_screen(&self) -> &xcb::Screen {
 		unsafe { std::mem::transmute(&self.screen) }
 	}
@@ -35,6 +113,12 @@ impl XcbBackend {
 		Ok(atom)
 	}
 
+	/// Reads a property starting `offset` 32-bit units in. `length` is how many 32-bit units to read at first; if the
+	/// property holds more after that, the rest is read as well, so the result always runs to the end of the property.
+	///
+	/// Properties of type `INCR`, which selection owners use for data too large to send at once, are read in chunks as the
+	/// owner provides them. This needs `window` to select `PropertyChange` events, which the clipboard's selection window
+	/// does.
 	pub fn get_property<F: XPropertyFormat, T: XProperty<F>>(
 		&self,
 		window: xcb::Window,
@@ -43,36 +127,111 @@ impl XcbBackend {
 		offset: u32,
 		length: u32,
 	) -> Result<Vec<T>, XcbBackendError> {
-		let property_reply = xcb::get_property(self.conn.as_ref(), false, window, property, property_type, offset, length)
-			.get_reply()
-			.map_err(|_| XcbBackendError::Unknown)?;
+		let read = |length| {
+			xcb::get_property(self.conn.as_ref(), false, window, property, property_type, offset, length)
+				.get_reply()
+				.map_err(|_| XcbBackendError::Unknown)
+		};
+		let mut property_reply = read(length)?;
 
 		log::trace!("Target type: {}, got type: {}", property_type, property_reply.type_());
 
+		let found_type = property_reply.type_();
+		if found_type != property_type && found_type != xcb::NONE && found_type == self.intern_atom("INCR")? {
+			let chunks = self.read_incr_property(window, property, property_type)?;
+			return T::from_property_replies(self, chunks, offset, length);
+		}
+		// A mismatched type reports the whole property as remaining without returning any of it
+		if property_reply.bytes_after() > 0 && (found_type == property_type || property_type == xcb::ATOM_ANY) {
+			property_reply = read(length + (property_reply.bytes_after() + 3) / 4)?;
+		}
+
 		let prop = T::from_property_reply(self, property_reply, offset, length)?;
 		Ok(prop)
 	}
 
+	/// Removes a property from the window. Deleting a property that isn't set does nothing.
+	pub fn delete_property(&self, window: xcb::Window, property: xcb::Atom) -> Result<(), XcbBackendError> {
+		xcb::delete_property_checked(self.conn.as_ref(), window, property)
+			.request_check()
+			.map_err(|e| {
+				log::error!("Failed to delete property {}: {}", property, e);
+				XcbBackendError::Unknown
+			})
+	}
+
+	/// Lists the properties currently set on the window.
+	pub fn list_properties(&self, window: xcb::Window) -> Result<Vec<xcb::Atom>, XcbBackendError> {
+		let reply = xcb::list_properties(self.conn.as_ref(), window).get_reply().map_err(|e| {
+			log::error!("Failed to list properties: {}", e);
+			XcbBackendError::Unknown
+		})?;
+		Ok(reply.atoms().to_vec())
+	}
+
+	/// Gets the name an atom was interned with, the reverse of `intern_atom`.
+	pub fn get_atom_name(&self, atom: xcb::Atom) -> Result<String, XcbBackendError> {
+		let reply = xcb::get_atom_name(self.conn.as_ref(), atom).get_reply().map_err(|e| {
+			log::error!("Failed to get the name of atom {}: {}", atom, e);
+			XcbBackendError::Unknown
+		})?;
+		Ok(reply.name().to_owned())
+	}
+
+	/// Lists the names of the properties set on the window, which is mostly useful for debugging.
+	pub fn list_property_names(&self, window: xcb::Window) -> Result<Vec<String>, XcbBackendError> {
+		self.list_properties(window)?
+			.into_iter()
+			.map(|atom| self.get_atom_name(atom))
+			.collect()
+	}
+
 	pub fn set_property<F: XPropertyFormat, T: XProperty<F>>(
 		&self,
 		window: xcb::Window,
 		property: xcb::Atom,
 		values: Vec<T>,
 	) -> Result<(), XcbBackendError> {
+		// The length of the ChangeProperty request header in 4-byte units
+		const CHANGE_PROPERTY_HEADER_LEN: usize = 6;
+
 		let value = T::to_property_value(self, values)?;
-		xcb::change_property(
-			self.conn.as_ref(),
-			xcb::PROP_MODE_REPLACE as u8,
-			window,
-			property,
-			T::property_type().atom(self),
-			F::format() as u8,
-			&value,
-		);
+		let property_type = T::property_type().atom(self)?;
+
+		// Values that don't fit in a single request are written in chunks, appending after the first
+		let max_request_bytes = (self.conn.get_maximum_request_length() as usize).saturating_sub(CHANGE_PROPERTY_HEADER_LEN) * 4;
+		let chunk_len = max_request_bytes / F::size();
+		if chunk_len == 0 {
+			return Err(XcbBackendError::RequestTooLarge);
+		}
+
+		let mut chunks = value.chunks(chunk_len);
+		let mut mode = xcb::PROP_MODE_REPLACE;
+		let mut chunk = chunks.next().unwrap_or(&[]);
+		loop {
+			xcb::change_property(
+				self.conn.as_ref(),
+				mode as u8,
+				window,
+				property,
+				property_type,
+				F::format() as u8,
+				chunk,
+			);
+			mode = xcb::PROP_MODE_APPEND;
+			chunk = match chunks.next() {
+				Some(chunk) => chunk,
+				None => break,
+			};
+		}
 		Ok(())
 	}
 
-	pub fn create_window(&self, dims: WindowDims) -> Result<xcb::Window, XcbBackendError> {
+	/// Creates an unmapped window with the given geometry. The position is flagged to the window manager as chosen by the
+	/// program; pass `user_position` if the user explicitly asked for it (say on the command line) to flag it as theirs
+	/// too, which window managers are more likely to honor.
+	pub fn create_window(&self, dims: WindowDims, user_position: bool) -> Result<xcb::Window, XcbBackendError> {
+		let (x, y, width, height) = checked_geometry(dims)?;
 		let conn = self.conn.as_ref();
 		let wid = conn.generate_id();
 		let screen = self.get_screen();
@@ -102,9 +261,15 @@ impl XcbBackend {
 			(
 				xcb::CW_EVENT_MASK,
 				xcb::EVENT_MASK_EXPOSURE
+					| xcb::EVENT_MASK_KEY_PRESS
+					| xcb::EVENT_MASK_KEY_RELEASE
 					| xcb::EVENT_MASK_BUTTON_PRESS
 					| xcb::EVENT_MASK_BUTTON_RELEASE
-					| xcb::EVENT_MASK_STRUCTURE_NOTIFY,
+					| xcb::EVENT_MASK_POINTER_MOTION
+					| xcb::EVENT_MASK_ENTER_WINDOW
+					| xcb::EVENT_MASK_LEAVE_WINDOW
+					| xcb::EVENT_MASK_STRUCTURE_NOTIFY
+					| xcb::EVENT_MASK_FOCUS_CHANGE,
 			),
 			//(xcb::CW_OVERRIDE_REDIRECT, 1),
 		];
@@ -113,10 +278,10 @@ impl XcbBackend {
 			32,
 			wid,
 			screen.root(),
-			dims.x as i16,
-			dims.y as i16,
-			dims.width as u16,
-			dims.height as u16,
+			x,
+			y,
+			width,
+			height,
 			0,
 			xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
 			self.visual_type.visual_id(),
@@ -133,9 +298,35 @@ impl XcbBackend {
 		let wm_protocols_atom: xcb::Atom = self.intern_atom("WM_PROTOCOLS")?;
 		self.set_property(wid, wm_protocols_atom, vec![AtomProperty(self.wm_delete_window_atom)])?;
 
+		// Most window managers ignore the position a window is created at and apply their own placement policy unless the
+		// position is flagged as requested in WM_NORMAL_HINTS. Tiling window managers may still override it.
+		let mut flags = WmSizeHints::P_POSITION | WmSizeHints::P_SIZE;
+		if user_position {
+			flags |= WmSizeHints::US_POSITION;
+		}
+		let size_hints = WmSizeHints {
+			flags,
+			x: dims.x,
+			y: dims.y,
+			width: dims.width,
+			height: dims.height,
+			..WmSizeHints::default()
+		};
+		self.set_property(wid, xcb::ATOM_WM_NORMAL_HINTS, vec![size_hints])?;
+
 		Ok(wid)
 	}
 
+	/// Wraps a window created by `create_window` for use through `WindowBackend`.
+	fn wrap_window(&self, window: xcb::Window, size: (u32, u32)) -> XcbWindow {
+		XcbWindow {
+			window,
+			size,
+			visual_id: self.visual_type.visual_id(),
+			conn: Arc::clone(&self.conn),
+		}
+	}
+
 	pub fn configure_window(&self, window: xcb::Window, args: &[ConfigValue]) -> Result<(), XcbBackendError> {
 		let xcb_config_values = args.iter().map(|c| (c.as_key(), c.as_value())).collect::<Vec<_>>();
 		let cookie = xcb::configure_window(self.conn.as_ref(), window, &xcb_config_values);
@@ -152,164 +343,881 @@ impl XcbBackend {
 			XcbBackendError::Unknown
 		})
 	}
-}
 
-#[test]
-fn window_prop_test() {
-	fn inner(backend: &XcbBackend, window: xcb::Window) {
-		let tree_cookie = xcb::query_tree(backend.conn.as_ref(), window);
-		let tree_reply = tree_cookie.get_reply().unwrap();
-		let children = tree_reply.children();
-		let window_type_atom = backend.intern_atom("_NET_WM_WINDOW_TYPE").unwrap();
-		let window_class_atom = backend.intern_atom("WM_CLASS").unwrap();
-
-		for child in children {
-			println!("window id: 0x{:x}, parent id: 0x{:x}", *child, window);
-			let window_class = backend.get_property::<_, String>(*child, window_class_atom, xcb::ATOM_STRING, 0, 5000);
-			println!("Window class: {:?}", window_class);
-			let window_type = backend
-				.get_property::<_, AtomProperty>(*child, window_type_atom, xcb::ATOM_ATOM, 0, 1)
-				.map(|v| v.into_iter().map(|a| a.0).collect::<Vec<_>>());
-			println!("Window type: {:?}", window_type);
-
-			let window_class = window_class.unwrap();
-			if window_class.get(0).map(|s| s.as_str()) == Some("kitty") {
-				let custom_atom_atom = backend.intern_atom("CUSTOM_KITTY_ATOM_BRASS").unwrap();
-				let custom_utf8_atom = backend.intern_atom("CUSTOM_UTF8_ATOM_BRASS").unwrap();
-				backend
-					.set_property::<_, AtomProperty>(
-						*child,
-						custom_atom_atom,
-						vec![AtomProperty(xcb::ATOM_ATOM), AtomProperty(xcb::ATOM_STRING)],
-					)
-					.unwrap();
-				backend
-					.set_property::<_, String>(*child, custom_utf8_atom, vec![String::from("nice"), String::from("meme")])
-					.unwrap();
+	/// Adds WM_TAKE_FOCUS to the WM_PROTOCOLS of the window, for windows using the locally or globally active input model.
+	/// The window manager then asks the window to take focus with a client message, which is answered in
+	/// `get_window_events`.
+	pub fn enable_take_focus(&self, window: xcb::Window) -> Result<(), XcbBackendError> {
+		let wm_protocols_atom: xcb::Atom = self.intern_atom("WM_PROTOCOLS")?;
+		self.set_property(
+			window,
+			wm_protocols_atom,
+			vec![
+				AtomProperty(self.wm_delete_window_atom),
+				AtomProperty(self.wm_take_focus_atom),
+			],
+		)
+	}
+
+	/// Sets the cursor shown over the window to a custom image, given as tightly packed 8-bit straight-alpha RGBA pixels.
+	/// `hotspot` is the point in the image that is positioned at the pointer location.
+	pub fn set_custom_cursor(
+		&self,
+		window: xcb::Window,
+		width: u16,
+		height: u16,
+		hotspot: (u16, u16),
+		rgba: &[u8],
+	) -> Result<(), XcbBackendError> {
+		let conn = self.conn.as_ref();
+		if rgba.len() != width as usize * height as usize * 4 {
+			return Err(XcbBackendError::Other(format!(
+				"Cursor image data has length {} but should be {} for a {}x{} image",
+				rgba.len(),
+				width as usize * height as usize * 4,
+				width,
+				height
+			)));
+		}
+
+		// Find the standard 32-bit ARGB picture format
+		let formats_reply = xcb::render::query_pict_formats(conn).get_reply().map_err(|e| {
+			log::error!("Failed to query XRender picture formats: {}", e);
+			XcbBackendError::Unknown
+		})?;
+		let format = formats_reply
+			.formats()
+			.find(|format| {
+				let direct = format.direct();
+				format.type_() == xcb::render::PICT_TYPE_DIRECT as u8
+					&& format.depth() == 32
+					&& direct.alpha_shift() == 24
+					&& direct.alpha_mask() == 0xff
+					&& direct.red_shift() == 16
+					&& direct.green_shift() == 8
+					&& direct.blue_shift() == 0
+			})
+			.map(|format| format.id())
+			.ok_or_else(|| XcbBackendError::Other(String::from("No ARGB32 picture format available")))?;
+
+		let data =
+			crate::drawing::cairo::rgba_to_argb32(rgba, width as u32, height as u32, width as usize * 4, AlphaMode::Straight);
+
+		let pixmap = conn.generate_id();
+		xcb::create_pixmap(conn, 32, pixmap, self.get_screen().root(), width, height);
+		let gc = conn.generate_id();
+		xcb::create_gc(conn, gc, pixmap, &[]);
+		xcb::put_image(
+			conn,
+			xcb::IMAGE_FORMAT_Z_PIXMAP as u8,
+			pixmap,
+			gc,
+			width,
+			height,
+			0,
+			0,
+			0,
+			32,
+			&data,
+		);
+		let picture = conn.generate_id();
+		xcb::render::create_picture(conn, picture, pixmap, format, &[]);
+		let cursor = conn.generate_id();
+		xcb::render::create_cursor(conn, cursor, picture, hotspot.0, hotspot.1);
+
+		let result = xcb::change_window_attributes_checked(conn, window, &[(xcb::CW_CURSOR, cursor)])
+			.request_check()
+			.map_err(|e| {
+				log::error!("Failed to set custom cursor: {}", e);
+				XcbBackendError::Unknown
+			});
+
+		// The server keeps the cursor alive for as long as the window uses it
+		xcb::free_cursor(conn, cursor);
+		xcb::render::free_picture(conn, picture);
+		xcb::free_gc(conn, gc);
+		xcb::free_pixmap(conn, pixmap);
+
+		result
+	}
+
+	/// Gets whether Caps Lock and Num Lock are currently on.
+	pub fn get_lock_state(&self) -> Result<LockState, XcbBackendError> {
+		Ok(self.query_modifiers()?.lock_state())
+	}
+
+	/// Gets the modifier keys that are currently held, without waiting for an input event.
+	pub fn query_modifiers(&self) -> Result<Modifiers, XcbBackendError> {
+		let reply = xcb::query_pointer(self.conn.as_ref(), self.get_screen().root())
+			.get_reply()
+			.map_err(|e| {
+				log::error!("Failed to query pointer: {}", e);
+				XcbBackendError::Unknown
+			})?;
+		Ok(modifiers_from_mask(reply.mask()))
+	}
+
+	/// Gets the position and size of the primary monitor, falling back to the whole screen if RandR doesn't report one.
+	pub fn get_primary_monitor_dims(&self) -> Result<WindowDims, XcbBackendError> {
+		let conn = self.conn.as_ref();
+		let screen = self.get_screen();
+		let screen_dims = WindowDims {
+			x: 0,
+			y: 0,
+			width: screen.width_in_pixels() as u32,
+			height: screen.height_in_pixels() as u32,
+		};
+
+		let output = match xcb::randr::get_output_primary(conn, screen.root()).get_reply() {
+			Ok(reply) if reply.output() != xcb::NONE => reply.output(),
+			_ => return Ok(screen_dims),
+		};
+		let crtc = match xcb::randr::get_output_info(conn, output, xcb::CURRENT_TIME).get_reply() {
+			Ok(reply) if reply.crtc() != xcb::NONE => reply.crtc(),
+			_ => return Ok(screen_dims),
+		};
+		let crtc_info = xcb::randr::get_crtc_info(conn, crtc, xcb::CURRENT_TIME)
+			.get_reply()
+			.map_err(|e| {
+				log::error!("Failed to get CRTC info: {}", e);
+				XcbBackendError::Unknown
+			})?;
+		Ok(WindowDims {
+			x: crtc_info.x() as i32,
+			y: crtc_info.y() as i32,
+			width: crtc_info.width() as u32,
+			height: crtc_info.height() as u32,
+		})
+	}
+
+	/// Gets the visual type a window was actually created with, which may differ from the backend's default 32-bit visual
+	/// for windows that weren't created by the backend. Lookups are cached by visual id.
+	pub fn get_window_visual_type(&self, window: xcb::Window) -> Result<xcb::Visualtype, XcbBackendError> {
+		let visual_id = xcb::get_window_attributes(self.conn.as_ref(), window)
+			.get_reply()
+			.map_err(|e| {
+				log::error!("Failed to get window attributes: {}", e);
+				XcbBackendError::Unknown
+			})?
+			.visual();
+		if let Some(visual_type) = self.visual_types.borrow().get(&visual_id) {
+			return Ok(*visual_type);
+		}
+
+		let visual_type = self
+			.get_screen()
+			.allowed_depths()
+			.flat_map(|depth| depth.visuals())
+			.find(|visual_type| visual_type.visual_id() == visual_id)
+			.ok_or_else(|| XcbBackendError::Other(format!("Visual 0x{:x} not found on screen", visual_id)))?;
+		self.visual_types.borrow_mut().insert(visual_id, visual_type);
+		Ok(visual_type)
+	}
+
+	/// Sets the visible shape of the window to the union of `rects`, in window coordinates. Areas outside the shape are
+	/// neither drawn nor receive input.
+	pub fn set_window_shape(&self, window: xcb::Window, rects: &[Rect]) -> Result<(), XcbBackendError> {
+		let rectangles = rects
+			.iter()
+			.map(|rect| {
+				xcb::Rectangle::new(
+					rect.x.round() as i16,
+					rect.y.round() as i16,
+					rect.width.round() as u16,
+					rect.height.round() as u16,
+				)
+			})
+			.collect::<Vec<_>>();
+		xcb::shape::rectangles_checked(
+			self.conn.as_ref(),
+			xcb::shape::SO_SET as u8,
+			xcb::shape::SK_BOUNDING as u8,
+			xcb::CLIP_ORDERING_UNSORTED as u8,
+			window,
+			0,
+			0,
+			&rectangles,
+		)
+		.request_check()
+		.map_err(|e| {
+			log::error!("Failed to set window shape: {}", e);
+			XcbBackendError::Unknown
+		})
+	}
+
+	/// Restores the default rectangular shape of the window.
+	pub fn clear_window_shape(&self, window: xcb::Window) -> Result<(), XcbBackendError> {
+		xcb::shape::mask_checked(
+			self.conn.as_ref(),
+			xcb::shape::SO_SET as u8,
+			xcb::shape::SK_BOUNDING as u8,
+			window,
+			0,
+			0,
+			xcb::NONE,
+		)
+		.request_check()
+		.map_err(|e| {
+			log::error!("Failed to clear window shape: {}", e);
+			XcbBackendError::Unknown
+		})
+	}
+
+	/// Asks the compositor to blur whatever is behind the translucent parts of the window, using the
+	/// `_KDE_NET_WM_BLUR_BEHIND_REGION` hint with an empty region, meaning the whole window. This is compositor-specific
+	/// (KWin and picom understand it) and does nothing when no supporting compositor is running.
+	pub fn set_blur_behind(&self, window: xcb::Window, enabled: bool) -> Result<(), XcbBackendError> {
+		let blur_atom = self.intern_atom("_KDE_NET_WM_BLUR_BEHIND_REGION")?;
+		if enabled {
+			self.set_property::<_, CardinalProperty>(window, blur_atom, Vec::new())
+		} else {
+			self.delete_property(window, blur_atom)
+		}
+	}
+
+	/// Starts watching for changes of the CLIPBOARD selection owner, which are reported as `WindowEvent::ClipboardChanged`.
+	/// This requires the XFIXES extension.
+	pub fn watch_clipboard(&self) -> Result<(), XcbBackendError> {
+		let conn = self.conn.as_ref();
+		let first_event = conn
+			.get_extension_data(xcb::xfixes::id())
+			.filter(|data| data.present())
+			.map(|data| data.first_event())
+			.ok_or_else(|| XcbBackendError::Other(String::from("XFIXES extension is not available")))?;
+		// The version has to be negotiated before any other XFIXES request is made
+		xcb::xfixes::query_version(conn, 5, 0).get_reply().map_err(|e| {
+			log::error!("Failed to query XFIXES version: {}", e);
+			XcbBackendError::Unknown
+		})?;
+
+		let clipboard_atom = self.intern_atom("CLIPBOARD")?;
+		xcb::xfixes::select_selection_input_checked(
+			conn,
+			self.get_screen().root(),
+			clipboard_atom,
+			xcb::xfixes::SELECTION_EVENT_MASK_SET_SELECTION_OWNER
+				| xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_WINDOW_DESTROY
+				| xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_CLIENT_CLOSE,
+		)
+		.request_check()
+		.map_err(|e| {
+			log::error!("Failed to watch clipboard: {}", e);
+			XcbBackendError::Unknown
+		})?;
+
+		self.xfixes_first_event.set(Some(first_event));
+		Ok(())
+	}
+
+	/// Gets the position and size of every active monitor.
+	pub fn get_monitors(&self) -> Result<Vec<WindowDims>, XcbBackendError> {
+		let conn = self.conn.as_ref();
+		let resources = xcb::randr::get_screen_resources_current(conn, self.get_screen().root())
+			.get_reply()
+			.map_err(|e| {
+				log::error!("Failed to get screen resources: {}", e);
+				XcbBackendError::Unknown
+			})?;
+		let cookies = resources
+			.crtcs()
+			.iter()
+			.map(|crtc| xcb::randr::get_crtc_info(conn, *crtc, resources.config_timestamp()))
+			.collect::<Vec<_>>();
+		let mut monitors = Vec::new();
+		for cookie in cookies {
+			let crtc_info = cookie.get_reply().map_err(|e| {
+				log::error!("Failed to get CRTC info: {}", e);
+				XcbBackendError::Unknown
+			})?;
+			// Disabled CRTCs have no size
+			if crtc_info.width() == 0 || crtc_info.height() == 0 {
+				continue;
+			}
+			monitors.push(WindowDims {
+				x: crtc_info.x() as i32,
+				y: crtc_info.y() as i32,
+				width: crtc_info.width() as u32,
+				height: crtc_info.height() as u32,
+			});
+		}
+		Ok(monitors)
+	}
+
+	/// Gets the work area of the current desktop from the `_NET_WORKAREA` property of the root window, which is the part of
+	/// the screen not covered by panels or docks. Returns `None` if the window manager doesn't set it.
+	pub fn get_desktop_work_area(&self) -> Result<Option<WindowDims>, XcbBackendError> {
+		let root = self.get_screen().root();
+		let current_desktop_atom = self.intern_atom("_NET_CURRENT_DESKTOP")?;
+		let current_desktop = self
+			.get_property::<_, CardinalProperty>(root, current_desktop_atom, xcb::ATOM_CARDINAL, 0, 1)
+			.ok()
+			.and_then(|desktop| desktop.get(0).map(|desktop| desktop.0))
+			.unwrap_or(0);
+
+		let work_area_atom = self.intern_atom("_NET_WORKAREA")?;
+		let work_area =
+			match self.get_property::<_, CardinalProperty>(root, work_area_atom, xcb::ATOM_CARDINAL, current_desktop * 4, 4) {
+				Ok(work_area) => work_area,
+				Err(XcbBackendError::PropertyTypeMismatch { found: xcb::NONE, .. }) => return Ok(None),
+				Err(e) => return Err(e),
+			};
+		// The property continues with the work areas of the following desktops
+		match work_area.get(..4) {
+			Some([x, y, width, height]) => Ok(Some(WindowDims {
+				x: x.0 as i32,
+				y: y.0 as i32,
+				width: width.0,
+				height: height.0,
+			})),
+			_ => Ok(None),
+		}
+	}
+
+	/// Sends a 32-bit format client message to `window`.
+	pub fn send_client_message(
+		&self,
+		window: xcb::Window,
+		message_type: xcb::Atom,
+		data: [u32; 5],
+		propagate: bool,
+		event_mask: u32,
+	) -> Result<(), XcbBackendError> {
+		self.send_client_message_to(window, window, message_type, data, propagate, event_mask)
+	}
+
+	/// Sends a 32-bit format client message about `window` to the root window, which is how EWMH requests to the window
+	/// manager are made.
+	pub fn send_root_message(&self, window: xcb::Window, message_type: xcb::Atom, data: [u32; 5]) -> Result<(), XcbBackendError> {
+		self.send_client_message_to(
+			self.get_screen().root(),
+			window,
+			message_type,
+			data,
+			false,
+			xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT | xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY,
+		)
+	}
+
+	fn send_client_message_to(
+		&self,
+		destination: xcb::Window,
+		window: xcb::Window,
+		message_type: xcb::Atom,
+		data: [u32; 5],
+		propagate: bool,
+		event_mask: u32,
+	) -> Result<(), XcbBackendError> {
+		let event = xcb::ClientMessageEvent::new(32, window, message_type, xcb::ClientMessageData::from_data32(data));
+		xcb::send_event_checked(self.conn.as_ref(), propagate, destination, event_mask, &event)
+			.request_check()
+			.map_err(|e| {
+				log::error!("Failed to send client message: {}", e);
+				XcbBackendError::Unknown
+			})
+	}
+
+	/// Adds or removes one of the `_NET_WM_STATE_*` states of a mapped window by asking the window manager.
+	pub fn set_wm_state(&self, window: xcb::Window, state: &str, enabled: bool) -> Result<(), XcbBackendError> {
+		const NET_WM_STATE_REMOVE: u32 = 0;
+		const NET_WM_STATE_ADD: u32 = 1;
+		// Source indication 1 means the request comes from a normal application
+		const SOURCE_APPLICATION: u32 = 1;
+
+		let wm_state_atom = self.intern_atom("_NET_WM_STATE")?;
+		let state_atom = self.intern_atom(state)?;
+		let action = if enabled { NET_WM_STATE_ADD } else { NET_WM_STATE_REMOVE };
+		self.send_root_message(window, wm_state_atom, [action, state_atom, 0, SOURCE_APPLICATION, 0])
+	}
+
+	/// Gets the next event that hasn't been handled yet without blocking, starting with deferred ones.
+	fn next_event(&self) -> Option<xcb::GenericEvent> {
+		let event = self.deferred_events.borrow_mut().pop_front();
+		event.or_else(|| self.conn.poll_for_event())
+	}
+
+	/// Translates an event from the server into a `WindowEvent`, updating the backend and window state it affects on the
+	/// way.
+	fn translate_event(&self, window: &mut XcbWindow, event: &xcb::GenericEvent) -> Option<WindowEvent> {
+		match event.response_type() & !0x80 {
+			xcb::BUTTON_PRESS | xcb::BUTTON_RELEASE => {
+				let button_event = unsafe { xcb::cast_event::<xcb::ButtonPressEvent>(event) };
+				self.last_input_time.set(button_event.time());
+				translate_button_event(button_event, self.stylus.borrow().current())
+			}
+			xcb::KEY_PRESS | xcb::KEY_RELEASE => {
+				let key_event = unsafe { xcb::cast_event::<xcb::KeyPressEvent>(event) };
+				self.last_input_time.set(key_event.time());
+				let modifiers = modifiers_from_mask(key_event.state());
+				Some(WindowEvent::Keyboard(KeyboardEvent {
+					state: if event.response_type() & !0x80 == xcb::KEY_PRESS {
+						PressState::Pressed
+					} else {
+						PressState::Released
+					},
+					key: self.lookup_key(key_event.detail(), modifiers),
+					scancode: key_event.detail() as u32,
+					modifiers,
+				}))
+			}
+			xcb::MAPPING_NOTIFY => {
+				let mapping_event = unsafe { xcb::cast_event::<xcb::MappingNotifyEvent>(event) };
+				if mapping_event.request() == xcb::MAPPING_KEYBOARD as u8 {
+					// Reloaded on the next key event
+					self.keyboard_mapping.replace(None);
+				}
+				None
+			}
+			// Grabs and ungrabs also generate crossing events without the pointer actually moving, so only normal
+			// crossings are reported
+			xcb::ENTER_NOTIFY => {
+				let enter_event = unsafe { xcb::cast_event::<xcb::EnterNotifyEvent>(event) };
+				if enter_event.mode() == xcb::NOTIFY_MODE_NORMAL as u8 {
+					Some(WindowEvent::MouseEnter)
+				} else {
+					None
+				}
+			}
+			xcb::LEAVE_NOTIFY => {
+				let leave_event = unsafe { xcb::cast_event::<xcb::LeaveNotifyEvent>(event) };
+				if leave_event.mode() == xcb::NOTIFY_MODE_NORMAL as u8 {
+					Some(WindowEvent::MouseExit)
+				} else {
+					None
+				}
+			}
+			xcb::MOTION_NOTIFY => {
+				let motion_event = unsafe { xcb::cast_event::<xcb::MotionNotifyEvent>(event) };
+				self.last_input_time.set(motion_event.time());
+				Some(WindowEvent::MouseMove(MouseMoveEvent {
+					pos: (motion_event.event_x() as f64, motion_event.event_y() as f64),
+					stylus: self.stylus.borrow().current(),
+				}))
+			}
+			xcb::EXPOSE => {
+				let expose_event = unsafe { xcb::cast_event::<xcb::ExposeEvent>(event) };
+				Some(WindowEvent::Expose {
+					region: Rect {
+						x: expose_event.x() as f64,
+						y: expose_event.y() as f64,
+						width: expose_event.width() as f64,
+						height: expose_event.height() as f64,
+					},
+					more_coming: expose_event.count() > 0,
+				})
+			}
+			xcb::SELECTION_REQUEST => {
+				let request_event = unsafe { xcb::cast_event::<xcb::SelectionRequestEvent>(event) };
+				if let Err(e) = self.handle_selection_request(request_event) {
+					log::error!("Failed to answer selection request: {:?}", e);
+				}
+				None
+			}
+			xcb::SELECTION_CLEAR => {
+				let clear_event = unsafe { xcb::cast_event::<xcb::SelectionClearEvent>(event) };
+				if let Err(e) = self.handle_selection_clear(clear_event) {
+					log::error!("Failed to handle selection clear: {:?}", e);
+				}
+				None
+			}
+			xcb::FOCUS_IN => Some(WindowEvent::FocusGained),
+			xcb::FOCUS_OUT => Some(WindowEvent::FocusLost),
+			xcb::DESTROY_NOTIFY => Some(WindowEvent::CloseHappened),
+			xcb::CONFIGURE_NOTIFY => {
+				let configure_event = unsafe { xcb::cast_event::<xcb::ConfigureNotifyEvent>(event) };
+				let size = (configure_event.width() as u32, configure_event.height() as u32);
+				// Moving or restacking the window also generates configure events, which shouldn't resize the surface
+				if configure_event.window() == window.window && size != window.size {
+					window.size = size;
+					Some(WindowEvent::ResizeHappened {
+						dims: (size.0 as f64, size.1 as f64),
+					})
+				} else {
+					None
+				}
+			}
+			xcb::MAP_NOTIFY => Some(WindowEvent::Shown),
+			xcb::UNMAP_NOTIFY => Some(WindowEvent::Hidden),
+			xcb::CLIENT_MESSAGE => {
+				log::debug!("Got client message");
+				let client_message_event = unsafe { xcb::cast_event::<xcb::ClientMessageEvent>(event) };
+				let data = client_message_event.data().data32();
+				if data[0] == self.wm_delete_window_atom {
+					Some(WindowEvent::CloseRequested)
+				} else if data[0] == self.wm_take_focus_atom {
+					// The second item is the timestamp of the event that triggered the focus change
+					xcb::set_input_focus(
+						self.conn.as_ref(),
+						xcb::INPUT_FOCUS_PARENT as u8,
+						client_message_event.window(),
+						data[1],
+					);
+					None
+				} else {
+					log::warn!("Got unknown client message");
+					None
+				}
+			}
+			event
+				if Some(event)
+					== self
+						.xfixes_first_event
+						.get()
+						.map(|first| first + xcb::xfixes::SELECTION_NOTIFY) =>
+			{
+				Some(WindowEvent::ClipboardChanged)
+			}
+			xcb::GE_GENERIC if self.handle_xinput_event(event) => None,
+			event => {
+				log::debug!("Got unhandled event of type {}", event);
+				None
+			}
+		}
+	}
+
+	/// Translates a keycode into a key using the server's keyboard mapping, which is loaded on first use.
+	fn lookup_key(&self, keycode: xcb::Keycode, modifiers: Modifiers) -> Key {
+		let mut keyboard_mapping = self.keyboard_mapping.borrow_mut();
+		if keyboard_mapping.is_none() {
+			*keyboard_mapping = KeyboardMapping::load(self.conn.as_ref()).ok();
+		}
+		keyboard_mapping
+			.as_ref()
+			.and_then(|mapping| mapping.keysym(keycode, modifiers))
+			.and_then(keysym_to_key)
+			.unwrap_or(Key::Unknown)
+	}
+
+	/// Walks up the window tree to find the ancestor of `window` that is a direct child of the root window. When a
+	/// reparenting window manager is running this is the frame window, otherwise it's the window itself.
+	pub fn get_toplevel_window(&self, window: xcb::Window) -> Result<xcb::Window, XcbBackendError> {
+		let root = self.get_screen().root();
+		let mut current = window;
+		loop {
+			let tree_reply = xcb::query_tree(self.conn.as_ref(), current).get_reply().map_err(|e| {
+				log::error!("Failed to query XCB window tree: {}", e);
+				XcbBackendError::Unknown
+			})?;
+			if tree_reply.parent() == root || tree_reply.parent() == xcb::NONE {
+				return Ok(current);
 			}
+			current = tree_reply.parent();
+		}
+	}
+
+	/// Gets the size of the decorations the window manager added around the window, as (left, right, top, bottom), from the
+	/// `_NET_FRAME_EXTENTS` property. Returns all zeros if the window manager doesn't set the property.
+	pub fn get_frame_extents(&self, window: xcb::Window) -> Result<(u32, u32, u32, u32), XcbBackendError> {
+		let frame_extents_atom = self.intern_atom("_NET_FRAME_EXTENTS")?;
+		let extents = match self.get_property::<_, CardinalProperty>(window, frame_extents_atom, xcb::ATOM_CARDINAL, 0, 4) {
+			Ok(extents) => extents,
+			Err(XcbBackendError::PropertyTypeMismatch { found: xcb::NONE, .. }) => return Ok((0, 0, 0, 0)),
+			Err(e) => return Err(e),
+		};
+		match extents.as_slice() {
+			[left, right, top, bottom] => Ok((left.0, right.0, top.0, bottom.0)),
+			_ => Err(XcbBackendError::Other(format!(
+				"_NET_FRAME_EXTENTS has {} values instead of 4",
+				extents.len()
+			))),
+		}
+	}
+
+	/// Translates a point relative to `window` into root (screen) coordinates.
+	pub fn window_to_root(&self, window: xcb::Window, x: f64, y: f64) -> Result<(i32, i32), XcbBackendError> {
+		self.translate_coordinates(window, self.get_screen().root(), x, y)
+	}
 
-			inner(backend, *child);
+	/// Translates a point in root (screen) coordinates into coordinates relative to `window`.
+	pub fn root_to_window(&self, window: xcb::Window, x: f64, y: f64) -> Result<(i32, i32), XcbBackendError> {
+		self.translate_coordinates(self.get_screen().root(), window, x, y)
+	}
+
+	fn translate_coordinates(
+		&self,
+		src_window: xcb::Window,
+		dst_window: xcb::Window,
+		x: f64,
+		y: f64,
+	) -> Result<(i32, i32), XcbBackendError> {
+		let reply = xcb::translate_coordinates(self.conn.as_ref(), src_window, dst_window, x.round() as i16, y.round() as i16)
+			.get_reply()
+			.map_err(|e| {
+				log::error!("Failed to translate coordinates: {}", e);
+				XcbBackendError::Unknown
+			})?;
+		Ok((reply.dst_x() as i32, reply.dst_y() as i32))
+	}
+
+	/// Reads the whole window tree below `root`, including the geometry and attributes of every window. The requests for
+	/// each level of the tree are pipelined, so this takes one round trip per level rather than per window. Windows that
+	/// are destroyed while the tree is being read are left out.
+	pub fn query_window_tree(&self, root: xcb::Window) -> Result<WindowNode, XcbBackendError> {
+		self.query_window_nodes(&[root])?
+			.pop()
+			.and_then(|node| node)
+			.ok_or_else(|| XcbBackendError::Other(format!("Window 0x{:x} does not exist", root)))
+	}
+
+	fn query_window_nodes(&self, windows: &[xcb::Window]) -> Result<Vec<Option<WindowNode>>, XcbBackendError> {
+		if windows.is_empty() {
+			return Ok(Vec::new());
 		}
+
+		let conn = self.conn.as_ref();
+		let cookies = windows
+			.iter()
+			.map(|&window| {
+				(
+					xcb::query_tree(conn, window),
+					xcb::get_geometry(conn, window),
+					xcb::get_window_attributes(conn, window),
+				)
+			})
+			.collect::<Vec<_>>();
+		let replies = windows
+			.iter()
+			.zip(cookies)
+			.map(|(&window, (tree_cookie, geometry_cookie, attributes_cookie))| {
+				match (
+					tree_cookie.get_reply(),
+					geometry_cookie.get_reply(),
+					attributes_cookie.get_reply(),
+				) {
+					(Ok(tree), Ok(geometry), Ok(attributes)) => Some((
+						window,
+						tree.children().to_vec(),
+						WindowDims {
+							x: geometry.x() as i32,
+							y: geometry.y() as i32,
+							width: geometry.width() as u32,
+							height: geometry.height() as u32,
+						},
+						WindowAttributes {
+							class: attributes.class(),
+							visual: attributes.visual(),
+							map_state: attributes.map_state(),
+							override_redirect: attributes.override_redirect(),
+						},
+					)),
+					_ => {
+						log::debug!("Window 0x{:x} disappeared while reading the window tree", window);
+						None
+					}
+				}
+			})
+			.collect::<Vec<_>>();
+
+		let all_children = replies
+			.iter()
+			.flatten()
+			.flat_map(|(_, children, _, _)| children.iter().cloned())
+			.collect::<Vec<_>>();
+		let mut child_nodes = self.query_window_nodes(&all_children)?.into_iter();
+
+		Ok(replies
+			.into_iter()
+			.map(|reply| {
+				reply.map(|(window, children, geometry, attributes)| WindowNode {
+					window,
+					children: child_nodes.by_ref().take(children.len()).flatten().collect(),
+					geometry,
+					attributes,
+				})
+			})
+			.collect())
 	}
+}
+
+#[cfg(feature = "display-tests")]
+#[test]
+fn window_prop_test() {
 	let backend = XcbBackend::init().unwrap();
 	let root = backend.screen.root();
+	let dims = WindowDims {
+		x: 10,
+		y: 20,
+		width: 64,
+		height: 48,
+	};
+	let window = XcbBackend::create_window(&backend, dims, false).unwrap();
 
-	let window_type_atom = backend.intern_atom("_NET_WM_WINDOW_TYPE").unwrap();
-	let window_class_atom = backend.intern_atom("WM_CLASS").unwrap();
+	let custom_atom_atom = backend.intern_atom("CUSTOM_ATOM_BRASS").unwrap();
+	let custom_utf8_atom = backend.intern_atom("CUSTOM_UTF8_ATOM_BRASS").unwrap();
+	backend
+		.set_property(
+			window,
+			custom_atom_atom,
+			vec![AtomProperty(xcb::ATOM_ATOM), AtomProperty(xcb::ATOM_STRING)],
+		)
+		.unwrap();
+	backend
+		.set_property(window, custom_utf8_atom, vec![String::from("nice"), String::from("meme")])
+		.unwrap();
+	let atoms = backend
+		.get_property::<_, AtomProperty>(window, custom_atom_atom, xcb::ATOM_ATOM, 0, 2)
+		.unwrap();
+	assert_eq!(atoms, [AtomProperty(xcb::ATOM_ATOM), AtomProperty(xcb::ATOM_STRING)]);
+	let utf8_string_atom = backend.intern_atom("UTF8_STRING").unwrap();
+	let strings = backend
+		.get_property::<_, String>(window, custom_utf8_atom, utf8_string_atom, 0, 5000)
+		.unwrap();
+	assert_eq!(strings, ["nice", "meme"]);
 
-	println!("root id: 0x{:x}", root);
-	let window_class = backend.get_property::<_, String>(root, window_class_atom, xcb::ATOM_STRING, 0, 5000);
-	println!("Window class: {:?}", window_class);
-	let window_type = backend.get_property::<_, AtomProperty>(root, window_type_atom, xcb::ATOM_ATOM, 0, 1);
-	println!("Window type: {:?}", window_type);
+	let tree = backend.query_window_tree(root).unwrap();
+	assert_eq!(tree.window, root);
+	let node = tree.children.iter().find(|child| child.window == window).unwrap();
+	// It isn't mapped, so the window manager hasn't moved or reparented it
+	let geometry = node.geometry;
+	assert_eq!((geometry.x, geometry.y, geometry.width, geometry.height), (10, 20, 64, 48));
 
-	inner(&backend, root);
+	xcb::destroy_window(backend.conn.as_ref(), window);
+	backend.conn.flush();
 }
 
 pub struct XcbWindow {
 	pub window: xcb::Window,
+	/// The last size reported to the app, used to drop configure events that don't change the size.
+	size: (u32, u32),
+	visual_id: xcb::Visualid,
+	#[cfg_attr(not(feature = "raw-window-handle"), allow(dead_code))]
+	conn: Arc<xcb::Connection>,
+}
+
+impl XcbWindow {
+	/// The visual the window was created with.
+	pub fn visual_id(&self) -> xcb::Visualid {
+		self.visual_id
+	}
+}
+
+#[cfg(feature = "raw-window-handle")]
+unsafe impl raw_window_handle::HasRawWindowHandle for XcbWindow {
+	fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+		let mut handle = raw_window_handle::XcbHandle::empty();
+		handle.window = self.window;
+		handle.connection = self.conn.get_raw_conn() as *mut _;
+		handle.visual_id = self.visual_id;
+		raw_window_handle::RawWindowHandle::Xcb(handle)
+	}
+}
+
+/// A window and everything below it in the window tree, as read by `XcbBackend::query_window_tree`.
+#[derive(Debug, Clone)]
+pub struct WindowNode {
+	pub window: xcb::Window,
+	pub children: Vec<WindowNode>,
+	/// The position relative to the parent window and the size, excluding the border.
+	pub geometry: WindowDims,
+	pub attributes: WindowAttributes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowAttributes {
+	/// One of the `xcb::WINDOW_CLASS_*` constants.
+	pub class: u16,
+	pub visual: xcb::Visualid,
+	/// One of the `xcb::MAP_STATE_*` constants.
+	pub map_state: u8,
+	pub override_redirect: bool,
 }
 
 impl WindowBackend for XcbBackend {
 	type Window = XcbWindow;
+	type Id = xcb::Window;
 	type Error = XcbBackendError;
 
 	fn init() -> Result<Self, Self::Error> {
-		let (conn, screen_idx) = xcb::Connection::connect(None).map_err(|_| XcbBackendError::ConnectionFailed)?;
-		let screen: xcb::Screen<'static> =
-			unsafe { std::mem::transmute(conn.get_setup().roots().nth(screen_idx as usize).unwrap()) };
-		// Atom referring to string "WM_DELETE_WINDOW"
-		let wm_delete_window_atom: xcb::Atom = xcb::intern_atom(&conn, false, "WM_DELETE_WINDOW").get_reply().unwrap().atom();
+		XcbBackend::init_with_display(None).map(|(backend, _screen_idx)| backend)
+	}
 
-		let mut visual_type = None;
-		'outer: for depth in screen.allowed_depths() {
-			if depth.depth() != 32 {
-				continue;
-			}
+	fn create_window(&self, title: &str, dims: WindowDims) -> Result<Self::Window, Self::Error> {
+		let window = XcbBackend::create_window(self, dims, false)?;
 
-			for test_visual_type in depth.visuals() {
-				visual_type = Some(test_visual_type);
-				break 'outer;
-			}
-		}
-		let visual_type = visual_type.unwrap();
+		let xcb_window = self.wrap_window(window, (dims.width, dims.height));
+		self.set_window_title(&xcb_window, title)?;
+		self.map_window(window)?;
 
-		Ok(Self {
-			conn: Arc::new(conn),
-			screen,
-			wm_delete_window_atom,
-			visual_type,
-		})
+		log::info!("Created and mapped window successfully");
+
+		Ok(xcb_window)
 	}
 
-	fn create_window(&self, title: &str, dims: WindowDims) -> Result<Self::Window, Self::Error> {
-		let window = XcbBackend::create_window(self, dims)?;
+	fn create_splash_window(&self, title: &str, dims: (u32, u32)) -> Result<Self::Window, Self::Error> {
+		let monitor = self.get_primary_monitor_dims()?;
+		let window = XcbBackend::create_window(
+			self,
+			WindowDims {
+				x: monitor.x + (monitor.width as i32 - dims.0 as i32) / 2,
+				y: monitor.y + (monitor.height as i32 - dims.1 as i32) / 2,
+				width: dims.0,
+				height: dims.1,
+			},
+			false,
+		)?;
+
+		// The window type and state have to be set before mapping for the window manager to pick them up
+		let window_type_atom = self.intern_atom("_NET_WM_WINDOW_TYPE")?;
+		let splash_atom = self.intern_atom("_NET_WM_WINDOW_TYPE_SPLASH")?;
+		self.set_property(window, window_type_atom, vec![AtomProperty(splash_atom)])?;
+		let state_atom = self.intern_atom("_NET_WM_STATE")?;
+		let above_atom = self.intern_atom("_NET_WM_STATE_ABOVE")?;
+		self.set_property(window, state_atom, vec![AtomProperty(above_atom)])?;
+		let name_atom = self.intern_atom("_NET_WM_NAME")?;
+		self.set_property(window, name_atom, vec![String::from(title)])?;
 
 		self.map_window(window)?;
 
-		log::info!("Created and mapped window successfully");
+		Ok(self.wrap_window(window, dims))
+	}
 
-		Ok(XcbWindow { window })
+	fn window_id(&self, window: &Self::Window) -> Result<Self::Id, Self::Error> {
+		Ok(window.window)
 	}
 
-	fn get_window_events(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>) {
+	fn drain_events_immediate(&self, window: &mut Self::Window, f: &mut dyn FnMut(WindowEvent)) {
 		self.conn.flush();
-		while let Some(event) = self.conn.poll_for_event() {
-			let translated_e = match event.response_type() & !0x80 {
-				xcb::BUTTON_PRESS => {
-					let button_event = unsafe { xcb::cast_event::<xcb::ButtonPressEvent>(&event) };
-					Some(WindowEvent::MouseClick(MouseClickEvent {
-						state: PressState::Pressed,
-						button: {
-							log::debug!("Got button {}", button_event.detail());
-							match button_event.detail() {
-								_ => {}
-							};
-							MouseButton::Left
-						},
-						pos: (button_event.event_x() as f64, button_event.event_y() as f64),
-					}))
-				}
-				xcb::BUTTON_RELEASE => {
-					let button_event = unsafe { xcb::cast_event::<xcb::ButtonPressEvent>(&event) };
-					Some(WindowEvent::MouseClick(MouseClickEvent {
-						state: PressState::Released,
-						button: {
-							log::debug!("Got button {}", button_event.detail());
-							match button_event.detail() {
-								_ => {}
-							};
-							MouseButton::Left
-						},
-						pos: (button_event.event_x() as f64, button_event.event_y() as f64),
-					}))
-				}
-				xcb::EXPOSE => Some(WindowEvent::Expose),
-				xcb::DESTROY_NOTIFY => Some(WindowEvent::CloseHappened),
-				xcb::CLIENT_MESSAGE => {
-					log::debug!("Got client message");
-					let client_message_event = unsafe { xcb::cast_event::<xcb::ClientMessageEvent>(&event) };
-					if client_message_event.data().data32()[0] == self.wm_delete_window_atom {
-						Some(WindowEvent::CloseRequested)
-					} else {
-						log::warn!("Got unknown client message");
-						None
+		while let Some(event) = self.next_event() {
+			if let Some(e) = self.translate_event(window, &event) {
+				f(e);
+			}
+		}
+		self.conn.flush();
+	}
+
+	fn wait_events_timeout(&self, window: &mut Self::Window, event_buf: &mut VecDeque<WindowEvent>, deadline: Option<Instant>) {
+		let start_len = event_buf.len();
+		self.conn.flush();
+		let fd = unsafe { xcb::ffi::base::xcb_get_file_descriptor(self.conn.get_raw_conn()) };
+		// Events that don't translate to anything, like keyboard mapping changes, shouldn't end the wait
+		while event_buf.len() == start_len {
+			match self.next_event() {
+				Some(event) => event_buf.extend(self.translate_event(window, &event)),
+				None => {
+					if self.conn.has_error().is_err() {
+						log::error!("Lost the connection to the X server while waiting for events");
+						return;
+					}
+					if !poll_readable(fd, deadline) {
+						break;
 					}
 				}
-				event => {
-					log::debug!("Got unhandled event of type {}", event);
-					None
-				}
-			};
-			if let Some(e) = translated_e {
-				event_buf.push_back(e);
 			}
 		}
-		self.conn.flush();
+		self.get_window_events(window, event_buf);
 	}
 
-	fn set_window_size(&self, window: &Self::Window, dims: (u32, u32)) {
-		log::error!("Attempted to set window size but the operation is unsupported");
+	fn set_window_size(&self, window: &Self::Window, dims: (u32, u32)) -> Result<(), Self::Error> {
+		// X doesn't allow zero-sized windows
+		let (_, _, width, height) = checked_geometry(WindowDims {
+			x: 0,
+			y: 0,
+			width: dims.0.max(1),
+			height: dims.1.max(1),
+		})?;
+		self.configure_window(
+			window.window,
+			&[ConfigValue::Width(width as u32), ConfigValue::Height(height as u32)],
+		)
 	}
 
 	fn set_window_position(&self, window: &Self::Window, position: (i32, i32)) -> Result<(), Self::Error> {
@@ -329,49 +1237,251 @@ impl WindowBackend for XcbBackend {
 		Ok(())
 	}
 
+	fn set_decorations(&self, window: &Self::Window, decorated: bool) -> Result<(), Self::Error> {
+		let motif_hints_atom = self.intern_atom("_MOTIF_WM_HINTS")?;
+		let hints = MotifWmHints {
+			flags: MotifWmHints::DECORATIONS,
+			decorations: if decorated { MotifWmHints::DECOR_ALL } else { 0 },
+			..MotifWmHints::default()
+		};
+		self.set_property(window.window, motif_hints_atom, vec![hints])?;
+		self.conn.flush();
+		Ok(())
+	}
+
+	fn set_size_hints(
+		&self,
+		window: &Self::Window,
+		min: Option<(u32, u32)>,
+		max: Option<(u32, u32)>,
+		aspect: Option<(f64, f64)>,
+	) -> Result<(), Self::Error> {
+		// Aspect ratios are stored as fractions of integers
+		const ASPECT_DENOMINATOR: u32 = 10_000;
+		let fraction = |ratio: f64| ((ratio * ASPECT_DENOMINATOR as f64).round() as u32, ASPECT_DENOMINATOR);
+
+		// Keep the other hints, like the position flags set at creation
+		let mut hints = self
+			.get_property::<_, WmSizeHints>(window.window, xcb::ATOM_WM_NORMAL_HINTS, xcb::ATOM_WM_SIZE_HINTS, 0, 18)
+			.ok()
+			.and_then(|hints| hints.into_iter().next())
+			.unwrap_or_default();
+		hints.flags &= !(WmSizeHints::P_MIN_SIZE | WmSizeHints::P_MAX_SIZE | WmSizeHints::P_ASPECT);
+		if let Some((width, height)) = min {
+			hints.flags |= WmSizeHints::P_MIN_SIZE;
+			hints.min_width = width;
+			hints.min_height = height;
+		}
+		if let Some((width, height)) = max {
+			hints.flags |= WmSizeHints::P_MAX_SIZE;
+			hints.max_width = width;
+			hints.max_height = height;
+		}
+		if let Some((min_aspect, max_aspect)) = aspect {
+			hints.flags |= WmSizeHints::P_ASPECT;
+			hints.min_aspect = fraction(min_aspect);
+			hints.max_aspect = fraction(max_aspect);
+		}
+		self.set_property(window.window, xcb::ATOM_WM_NORMAL_HINTS, vec![hints])?;
+		self.conn.flush();
+		Ok(())
+	}
+
 	fn get_window_size(&self, window: &Self::Window) -> Result<(u32, u32), Self::Error> {
-		let geometry = xcb::get_geometry(self.conn.as_ref(), window.window).get_reply().unwrap();
+		let geometry = xcb::get_geometry(self.conn.as_ref(), window.window)
+			.get_reply()
+			.map_err(|e| {
+				log::error!("Failed to get window geometry: {}", e);
+				XcbBackendError::Unknown
+			})?;
 		Ok((geometry.width() as u32, geometry.height() as u32))
 	}
 
-	fn is_window_open(&self, window: &Self::Window) {
-		unimplemented!()
+	fn get_window_position(&self, window: &Self::Window) -> Result<(i32, i32), Self::Error> {
+		// The coordinates in the geometry reply are relative to the parent, which is the frame window when a reparenting
+		// window manager is running, so translate the origin to the root instead.
+		self.window_to_root(window.window, 0.0, 0.0)
+	}
+
+	fn get_work_area(&self, window: &Self::Window) -> Result<WindowDims, Self::Error> {
+		let (x, y) = self.get_window_position(window)?;
+		let (width, height) = self.get_window_size(window)?;
+		let center = (x + width as i32 / 2, y + height as i32 / 2);
+
+		let monitor = self
+			.get_monitors()?
+			.into_iter()
+			.min_by_key(|monitor| monitor.distance_squared_to(center))
+			.map(Ok)
+			.unwrap_or_else(|| self.get_primary_monitor_dims())?;
+		// _NET_WORKAREA covers all monitors, so only the part on the chosen monitor is usable
+		Ok(match self.get_desktop_work_area()? {
+			Some(work_area) => work_area.intersection(&monitor).unwrap_or(monitor),
+			None => monitor,
+		})
+	}
+
+	fn move_by(&self, window: &Self::Window, dx: i32, dy: i32) -> Result<(), Self::Error> {
+		// Window managers interpret configure requests against the position of the frame (for the default NorthWest
+		// gravity), so offset from the frame's position rather than the client's to avoid drifting by the decoration size.
+		let toplevel = self.get_toplevel_window(window.window)?;
+		let geometry = xcb::get_geometry(self.conn.as_ref(), toplevel).get_reply().map_err(|e| {
+			log::error!("Failed to get window geometry: {}", e);
+			XcbBackendError::Unknown
+		})?;
+		self.configure_window(
+			window.window,
+			&[
+				ConfigValue::X(geometry.x() as i32 + dx),
+				ConfigValue::Y(geometry.y() as i32 + dy),
+			],
+		)
+	}
+
+	fn activate(&self, window: &Self::Window) -> Result<(), Self::Error> {
+		let active_window_atom = self.intern_atom("_NET_ACTIVE_WINDOW")?;
+		// EWMH: source indication 1 is a normal application, and 2 is reserved for pagers and other tools acting for the
+		// user. Window managers use the timestamp of the user action that caused the request to decide whether to allow
+		// it or to prevent focus stealing instead.
+		const SOURCE_APPLICATION: u32 = 1;
+		let timestamp = self.last_input_time.get();
+		self.send_root_message(
+			window.window,
+			active_window_atom,
+			[SOURCE_APPLICATION, timestamp, xcb::NONE, 0, 0],
+		)
+	}
+
+	fn set_skip_taskbar(&self, window: &Self::Window, skip: bool) -> Result<(), Self::Error> {
+		self.set_wm_state(window.window, "_NET_WM_STATE_SKIP_TASKBAR", skip)
+	}
+
+	fn set_skip_pager(&self, window: &Self::Window, skip: bool) -> Result<(), Self::Error> {
+		self.set_wm_state(window.window, "_NET_WM_STATE_SKIP_PAGER", skip)
+	}
+
+	fn set_always_on_top(&self, window: &Self::Window, on_top: bool) -> Result<(), Self::Error> {
+		self.set_wm_state(window.window, "_NET_WM_STATE_ABOVE", on_top)
+	}
+
+	fn set_window_type(&self, window: &Self::Window, ty: WindowType) -> Result<(), Self::Error> {
+		let type_name = match ty {
+			WindowType::Normal => "_NET_WM_WINDOW_TYPE_NORMAL",
+			WindowType::Dialog => "_NET_WM_WINDOW_TYPE_DIALOG",
+			WindowType::Menu => "_NET_WM_WINDOW_TYPE_MENU",
+			WindowType::Tooltip => "_NET_WM_WINDOW_TYPE_TOOLTIP",
+			WindowType::Utility => "_NET_WM_WINDOW_TYPE_UTILITY",
+		};
+		let window_type_atom = self.intern_atom("_NET_WM_WINDOW_TYPE")?;
+		let type_atom = self.intern_atom(type_name)?;
+		self.set_property(window.window, window_type_atom, vec![AtomProperty(type_atom)])
+	}
+
+	fn set_opacity(&self, window: &Self::Window, opacity: f64) -> Result<(), Self::Error> {
+		let opacity_atom = self.intern_atom("_NET_WM_WINDOW_OPACITY")?;
+		let opacity = (opacity.max(0.0).min(1.0) * f64::from(u32::max_value())).round() as u32;
+		self.set_property(window.window, opacity_atom, vec![CardinalProperty(opacity)])
+	}
+
+	fn is_window_open(&self, window: &Self::Window) -> Result<bool, Self::Error> {
+		match xcb::get_geometry(self.conn.as_ref(), window.window).get_reply() {
+			Ok(_) => Ok(true),
+			// GetGeometry takes any drawable, so a destroyed window is reported as a bad drawable
+			Err(ref e) if e.error_code() == xcb::DRAWABLE || e.error_code() == xcb::WINDOW => Ok(false),
+			Err(e) => {
+				log::error!("Failed to query window geometry: {}", e);
+				Err(XcbBackendError::Unknown)
+			}
+		}
+	}
+
+	fn set_window_title(&self, window: &Self::Window, title: &str) -> Result<(), Self::Error> {
+		// WM_NAME is Latin-1 for older window managers, _NET_WM_NAME is UTF-8 and preferred by modern ones
+		let latin1_title = Latin1String {
+			data: title
+				.chars()
+				.map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' })
+				.collect(),
+		};
+		self.set_property(window.window, xcb::ATOM_WM_NAME, vec![latin1_title])?;
+		let net_wm_name_atom = self.intern_atom("_NET_WM_NAME")?;
+		self.set_property(window.window, net_wm_name_atom, vec![String::from(title)])
+	}
+
+	fn set_fullscreen(&self, window: &Self::Window, fullscreen: bool) -> Result<(), Self::Error> {
+		self.set_wm_state(window.window, "_NET_WM_STATE_FULLSCREEN", fullscreen)
+	}
+
+	fn set_minimized(&self, window: &Self::Window, minimized: bool) -> Result<(), Self::Error> {
+		if minimized {
+			// ICCCM: ask the window manager to change the window to the iconic state
+			const ICONIC_STATE: u32 = 3;
+			let change_state_atom = self.intern_atom("WM_CHANGE_STATE")?;
+			self.send_root_message(window.window, change_state_atom, [ICONIC_STATE, 0, 0, 0, 0])
+		} else {
+			// Mapping an iconic window returns it to the normal state
+			self.map_window(window.window)
+		}
+	}
+
+	fn set_maximized(&self, window: &Self::Window, maximized: bool) -> Result<(), Self::Error> {
+		const NET_WM_STATE_REMOVE: u32 = 0;
+		const NET_WM_STATE_ADD: u32 = 1;
+		const SOURCE_APPLICATION: u32 = 1;
+
+		// Both directions are changed in a single message so the window manager doesn't maximize in two steps
+		let wm_state_atom = self.intern_atom("_NET_WM_STATE")?;
+		let vert_atom = self.intern_atom("_NET_WM_STATE_MAXIMIZED_VERT")?;
+		let horz_atom = self.intern_atom("_NET_WM_STATE_MAXIMIZED_HORZ")?;
+		let action = if maximized { NET_WM_STATE_ADD } else { NET_WM_STATE_REMOVE };
+		self.send_root_message(
+			window.window,
+			wm_state_atom,
+			[action, vert_atom, horz_atom, SOURCE_APPLICATION, 0],
+		)
+	}
+
+	fn get_scale_factor(&self, _window: &Self::Window) -> Result<f64, Self::Error> {
+		// Derived from the physical size the X server reports for the screen, relative to the conventional 96 DPI, and
+		// rounded to a quarter so a slightly off physical size doesn't produce odd factors like 1.02
+		const BASE_DPI: f64 = 96.0;
+		const MM_PER_INCH: f64 = 25.4;
+
+		let screen = self.get_screen();
+		if screen.width_in_millimeters() == 0 {
+			return Ok(1.0);
+		}
+		let dpi = screen.width_in_pixels() as f64 * MM_PER_INCH / screen.width_in_millimeters() as f64;
+		Ok(((dpi / BASE_DPI) * 4.0).round().max(4.0) / 4.0)
 	}
 
 	fn present(&self) {
+		self.swap_back_buffers();
 		self.conn.flush();
 	}
 
 	fn close(&self, window: Self::Window) {
+		self.free_back_buffer(window.window);
 		xcb::destroy_window_checked(self.conn.as_ref(), window.window);
 	}
 }
 
 impl SurfaceCreator<Self, CairoBackend> for XcbBackend {
-	fn create_surface(&self, args: &<XcbBackend as WindowBackend>::Window) -> <CairoBackend as DrawingBackend>::Surface {
-		let dims = self.get_window_size(args).unwrap();
-		log::trace!("Creating surface with dims {}x{}", dims.0, dims.1);
-		unsafe {
-			/* let screen = self.get_screen();
-			let mut visual_type = None;
-
-			// Gets the visual_type of the root window
-			for depth in screen.allowed_depths() {
-				for visual in depth.visuals() {
-					if visual.visual_id() == screen.root_visual() {
-						visual_type = Some(visual)
-					}
-				}
-			}
-
-			let mut visual_type = Box::leak(Box::new(visual_type.unwrap())); */
+	type Error = XcbBackendError;
 
-			// TODO: don't leak...????
-			let visual_type = Box::leak(Box::new(self.visual_type));
+	fn create_surface(&self, args: &<XcbBackend as WindowBackend>::Window) -> Result<CairoSurface, XcbBackendError> {
+		let dims = self.get_window_size(args)?;
+		log::trace!("Creating surface with dims {}x{}", dims.0, dims.1);
+		// Kept alive for as long as the surface, in case Cairo holds on to it
+		let visual_type = Rc::new(self.get_window_visual_type(args.window).unwrap_or_else(|e| {
+			log::warn!("Failed to look up window visual, using the default: {:?}", e);
+			self.visual_type
+		}));
 
+		unsafe {
 			let cairo_xcb_connection = cairo::XCBConnection::from_raw_none(self.conn.get_raw_conn() as *mut _);
-			let cairo_drawable = cairo::XCBDrawable(args.window);
-			let cairo_xcb_visualtype = cairo::XCBVisualType::from_raw_none(&mut visual_type.base as *mut _ as *mut _);
+			let cairo_drawable = cairo::XCBDrawable(self.surface_drawable(args.window));
+			let cairo_xcb_visualtype = cairo::XCBVisualType::from_raw_none(&visual_type.base as *const _ as *mut _);
 
 			let cairo_xcb_surface = cairo::XCBSurface::create(
 				&cairo_xcb_connection,
@@ -380,16 +1490,193 @@ impl SurfaceCreator<Self, CairoBackend> for XcbBackend {
 				dims.0 as i32,
 				dims.1 as i32,
 			);
+			cairo_xcb_surface.set_user_data(&VISUAL_TYPE_KEY, visual_type);
+			// Cairo finishes the surface against the connection when it's dropped, which may be after the backend is gone
+			cairo_xcb_surface.set_user_data(&CONNECTION_KEY, Rc::new(self.conn.clone()));
+
+			Ok(CairoSurface::from_surface((*cairo_xcb_surface).clone()))
+		}
+	}
+}
+
+/// The visual a surface was created with, owned by the surface and freed along with it.
+static VISUAL_TYPE_KEY: cairo::UserDataKey<xcb::Visualtype> = cairo::UserDataKey::new();
 
-			let surface = CairoSurface::from_surface(cairo::Surface::from_raw_none(cairo_xcb_surface.to_raw_none()));
+/// The connection a surface draws through, kept open for as long as the surface. Cairo frees user data only after
+/// finishing the surface.
+static CONNECTION_KEY: cairo::UserDataKey<Arc<xcb::Connection>> = cairo::UserDataKey::new();
 
-			std::mem::forget(cairo_xcb_surface);
-			std::mem::forget(cairo_xcb_visualtype);
-			std::mem::forget(cairo_xcb_connection);
-			std::mem::forget(cairo_drawable);
+#[cfg(feature = "display-tests")]
+#[test]
+fn surfaces_are_freed() {
+	use crate::drawing::DrawingBackend;
 
-			surface
+	fn resident_pages() -> u64 {
+		let statm = std::fs::read_to_string("/proc/self/statm").unwrap();
+		statm.split_whitespace().nth(1).unwrap().parse().unwrap()
+	}
+
+	let backend = XcbBackend::init().unwrap();
+	let dims = WindowDims {
+		x: 0,
+		y: 0,
+		width: 64,
+		height: 64,
+	};
+	let create_and_drop = |count| {
+		for _ in 0..count {
+			let window = WindowBackend::create_window(&backend, "surfaces_are_freed", dims).unwrap();
+			let mut cairo = CairoBackend::new(backend.create_surface(&window).unwrap());
+			cairo.paint();
+			cairo.present();
+			drop(cairo);
+			backend.close(window);
 		}
+		backend.conn.flush();
+	};
+
+	// Warm up caches and allocator pools before measuring
+	create_and_drop(100);
+	let before = resident_pages();
+	create_and_drop(1000);
+	let growth_kib = resident_pages().saturating_sub(before) * 4;
+	assert!(growth_kib < 1024, "memory grew by {} KiB", growth_kib);
+}
+
+#[cfg(feature = "display-tests")]
+#[test]
+fn double_buffered_surfaces_draw_to_back_buffers() {
+	use crate::drawing::DrawingBackend;
+
+	let backend = XcbBackend::init().unwrap();
+	if !backend.set_double_buffering(true) {
+		// Without DBE surfaces draw to the window, which the other tests cover
+		return;
+	}
+	let dims = WindowDims {
+		x: 0,
+		y: 0,
+		width: 64,
+		height: 64,
+	};
+	let window = WindowBackend::create_window(&backend, "double_buffered_surfaces_draw_to_back_buffers", dims).unwrap();
+	let mut cairo = CairoBackend::new(backend.create_surface(&window).unwrap());
+	let back_buffer = backend.back_buffers.borrow()[&window.window];
+	assert_ne!(back_buffer, window.window);
+	// The back buffer is a drawable of its own with the window's size
+	let geometry = xcb::get_geometry(backend.conn.as_ref(), back_buffer).get_reply().unwrap();
+	assert_eq!((geometry.width(), geometry.height()), (64, 64));
+
+	cairo.paint();
+	cairo.present();
+	backend.present();
+	drop(cairo);
+	backend.close(window);
+	assert!(backend.back_buffers.borrow().is_empty());
+	assert!(backend.conn.has_error().is_ok());
+}
+
+/// Converts window dimensions to the integer types used by the X protocol, failing if they don't fit instead of
+/// wrapping around.
+fn checked_geometry(dims: WindowDims) -> Result<(i16, i16, u16, u16), XcbBackendError> {
+	use std::convert::TryFrom;
+
+	let invalid = |_| XcbBackendError::InvalidGeometry(dims);
+	Ok((
+		i16::try_from(dims.x).map_err(invalid)?,
+		i16::try_from(dims.y).map_err(invalid)?,
+		u16::try_from(dims.width).map_err(invalid)?,
+		u16::try_from(dims.height).map_err(invalid)?,
+	))
+}
+
+#[test]
+fn out_of_range_geometry_is_rejected() {
+	let dims = |x, y, width, height| WindowDims { x, y, width, height };
+	assert_eq!(checked_geometry(dims(-10, 20, 640, 480)).ok(), Some((-10, 20, 640, 480)));
+	assert!(checked_geometry(dims(40000, 0, 640, 480)).is_err());
+	assert!(checked_geometry(dims(0, -40000, 640, 480)).is_err());
+	assert!(checked_geometry(dims(0, 0, 70000, 480)).is_err());
+	assert!(checked_geometry(dims(0, 0, 640, 70000)).is_err());
+}
+
+/// Translates a button press or release, made with a stylus in the given state if it's `Some`. Buttons 4 through 7 are
+/// the scroll wheel, which always scrolls by lines and is reported on press only.
+fn translate_button_event(button_event: &xcb::ButtonPressEvent, stylus: Option<StylusState>) -> Option<WindowEvent> {
+	let pos = (button_event.event_x() as f64, button_event.event_y() as f64);
+	let state = match button_event.response_type() & !0x80 {
+		xcb::BUTTON_PRESS => PressState::Pressed,
+		_ => PressState::Released,
+	};
+	let button = match button_event.detail() {
+		1 => MouseButton::Left,
+		2 => MouseButton::Middle,
+		3 => MouseButton::Right,
+		4..=7 if state == PressState::Released => return None,
+		detail @ 4..=7 => {
+			let delta = match detail {
+				4 => (0.0, 1.0),
+				5 => (0.0, -1.0),
+				6 => (-1.0, 0.0),
+				_ => (1.0, 0.0),
+			};
+			return Some(WindowEvent::Scroll(ScrollEvent {
+				delta,
+				unit: ScrollUnit::Lines,
+				pos,
+			}));
+		}
+		detail => MouseButton::Other(detail),
+	};
+	Some(WindowEvent::MouseClick(MouseClickEvent {
+		state,
+		button,
+		pos,
+		stylus,
+		modifiers: modifiers_from_mask(button_event.state()),
+	}))
+}
+
+#[test]
+fn mouse_buttons_are_translated() {
+	let event = |response_type, detail| xcb::ButtonPressEvent::new(response_type, detail, 0, 0, 0, 0, 0, 0, 3, 4, 0, true);
+	match translate_button_event(&event(xcb::BUTTON_PRESS, 3), None) {
+		Some(WindowEvent::MouseClick(click_event)) => {
+			assert_eq!(click_event.button, MouseButton::Right);
+			assert_eq!(click_event.state, PressState::Pressed);
+			assert_eq!(click_event.pos, (3.0, 4.0));
+		}
+		evt => panic!("Expected a click, got {:?}", evt),
+	}
+	match translate_button_event(&event(xcb::BUTTON_RELEASE, 2), None) {
+		Some(WindowEvent::MouseClick(click_event)) => {
+			assert_eq!(click_event.button, MouseButton::Middle);
+			assert_eq!(click_event.state, PressState::Released);
+		}
+		evt => panic!("Expected a click, got {:?}", evt),
+	}
+	match translate_button_event(&event(xcb::BUTTON_PRESS, 5), None) {
+		Some(WindowEvent::Scroll(scroll_event)) => assert_eq!(scroll_event.delta, (0.0, -1.0)),
+		evt => panic!("Expected a scroll, got {:?}", evt),
+	}
+	assert_eq!(translate_button_event(&event(xcb::BUTTON_RELEASE, 5), None), None);
+	match translate_button_event(&event(xcb::BUTTON_PRESS, 9), None) {
+		Some(WindowEvent::MouseClick(click_event)) => assert_eq!(click_event.button, MouseButton::Other(9)),
+		evt => panic!("Expected a click, got {:?}", evt),
+	}
+}
+
+/// Converts an X modifier mask, as found in input events, into `Modifiers`. This uses the conventional mapping where Mod1
+/// is Alt, Mod2 is Num Lock and Mod4 is the logo key.
+fn modifiers_from_mask(mask: u16) -> Modifiers {
+	let mask = mask as u32;
+	Modifiers {
+		shift: mask & xcb::MOD_MASK_SHIFT != 0,
+		ctrl: mask & xcb::MOD_MASK_CONTROL != 0,
+		alt: mask & xcb::MOD_MASK_1 != 0,
+		logo: mask & xcb::MOD_MASK_4 != 0,
+		caps_lock: mask & xcb::MOD_MASK_LOCK != 0,
+		num_lock: mask & xcb::MOD_MASK_2 != 0,
 	}
 }
 
@@ -399,6 +1686,29 @@ pub enum XcbBackendError {
 	InternAtomFailed,
 	PropertyTypeMismatch { expected: xcb::Atom, found: xcb::Atom },
 	PropertyEncodingError,
+	RequestTooLarge,
+	InvalidGeometry(WindowDims),
+	Unsupported(&'static str),
 	Other(String),
 	Unknown,
 }
+
+impl std::fmt::Display for XcbBackendError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			XcbBackendError::ConnectionFailed => write!(f, "failed to connect to the X server"),
+			XcbBackendError::InternAtomFailed => write!(f, "failed to intern an atom"),
+			XcbBackendError::PropertyTypeMismatch { expected, found } => {
+				write!(f, "property has type atom {} instead of atom {}", found, expected)
+			}
+			XcbBackendError::PropertyEncodingError => write!(f, "property value is not encoded as expected"),
+			XcbBackendError::RequestTooLarge => write!(f, "request exceeds the maximum request length of the X server"),
+			XcbBackendError::InvalidGeometry(dims) => write!(f, "invalid window geometry {:?}", dims),
+			XcbBackendError::Unsupported(operation) => write!(f, "{} is not supported by the XCB backend", operation),
+			XcbBackendError::Other(message) => write!(f, "{}", message),
+			XcbBackendError::Unknown => write!(f, "unknown XCB backend error"),
+		}
+	}
+}
+
+impl std::error::Error for XcbBackendError {}
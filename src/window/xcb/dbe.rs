@@ -0,0 +1,87 @@
+use crate::window::xcb::ext::Extension;
+use crate::window::xcb::XcbBackend;
+
+/// The Double Buffer Extension, which the xcb bindings don't generate.
+static DBE: Extension = Extension::new(b"DOUBLE-BUFFER\0");
+
+const GET_VERSION: u8 = 0;
+const ALLOCATE_BACK_BUFFER_NAME: u8 = 1;
+const DEALLOCATE_BACK_BUFFER_NAME: u8 = 2;
+const SWAP_BUFFERS: u8 = 3;
+
+/// What a back buffer contains after it's swapped onto the screen. `Copied` keeps what was just presented, so frames can
+/// be drawn incrementally.
+const SWAP_ACTION_COPIED: u8 = 3;
+
+/// Checks that the server supports DBE and negotiates its version, which clients have to do before using it.
+fn query_support(conn: &xcb::Connection) -> bool {
+	if DBE.major_opcode(conn).is_none() {
+		return false;
+	}
+	// Header, then the major and minor version this was written against
+	let mut request = [0, 0, 0, 0, 1, 0, 0, 0];
+	match DBE.send_with_reply(conn, GET_VERSION, &mut request) {
+		Some(reply) => reply[8] >= 1,
+		None => false,
+	}
+}
+
+impl XcbBackend {
+	/// Opts into double buffering through the DBE extension. Surfaces created afterwards draw into a back buffer that
+	/// `present` swaps onto the screen for all double-buffered windows at once, so partially drawn frames never show.
+	/// Returns whether the server supports DBE; without it surfaces keep drawing to their window directly. Disabling it
+	/// only affects surfaces created afterwards.
+	pub fn set_double_buffering(&self, enabled: bool) -> bool {
+		if !enabled {
+			self.double_buffering.set(false);
+			return true;
+		}
+		let supported = query_support(&self.conn);
+		self.double_buffering.set(supported);
+		supported
+	}
+
+	/// Gets the drawable that surfaces for the window should draw to: its back buffer if double buffering is enabled, or
+	/// the window itself.
+	pub(crate) fn surface_drawable(&self, window: xcb::Window) -> xcb::Drawable {
+		if !self.double_buffering.get() {
+			return window;
+		}
+		if let Some(&back_buffer) = self.back_buffers.borrow().get(&window) {
+			return back_buffer;
+		}
+
+		let back_buffer = self.conn.generate_id();
+		let mut request = [0; 16];
+		request[4..8].copy_from_slice(&window.to_ne_bytes());
+		request[8..12].copy_from_slice(&back_buffer.to_ne_bytes());
+		request[12] = SWAP_ACTION_COPIED;
+		DBE.send(&self.conn, ALLOCATE_BACK_BUFFER_NAME, &mut request);
+		self.back_buffers.borrow_mut().insert(window, back_buffer);
+		back_buffer
+	}
+
+	/// Swaps the back buffers of all double-buffered windows onto the screen in one request.
+	pub(crate) fn swap_back_buffers(&self) {
+		let back_buffers = self.back_buffers.borrow();
+		if back_buffers.is_empty() {
+			return;
+		}
+		let mut request = vec![0; 8 + back_buffers.len() * 8];
+		request[4..8].copy_from_slice(&(back_buffers.len() as u32).to_ne_bytes());
+		for (swap_info, window) in request[8..].chunks_mut(8).zip(back_buffers.keys()) {
+			swap_info[0..4].copy_from_slice(&window.to_ne_bytes());
+			swap_info[4] = SWAP_ACTION_COPIED;
+		}
+		DBE.send(&self.conn, SWAP_BUFFERS, &mut request);
+	}
+
+	/// Frees the back buffer of a window that is about to be destroyed, if it has one.
+	pub(crate) fn free_back_buffer(&self, window: xcb::Window) {
+		if let Some(back_buffer) = self.back_buffers.borrow_mut().remove(&window) {
+			let mut request = [0; 8];
+			request[4..8].copy_from_slice(&back_buffer.to_ne_bytes());
+			DBE.send(&self.conn, DEALLOCATE_BACK_BUFFER_NAME, &mut request);
+		}
+	}
+}
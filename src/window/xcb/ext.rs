@@ -0,0 +1,117 @@
+use libc::{c_char, c_int, c_uint, c_void, iovec};
+use std::cell::UnsafeCell;
+use std::ptr;
+
+// Some extensions aren't generated by the xcb bindings, or are generated in a form that doesn't compile, so their
+// requests are built by hand and sent through libxcb directly, the way the C bindings generated for them do.
+
+#[repr(C)]
+struct RawExtension {
+	name: *const c_char,
+	global_id: c_int,
+}
+
+#[repr(C)]
+struct ProtocolRequest {
+	count: usize,
+	ext: *mut RawExtension,
+	opcode: u8,
+	isvoid: u8,
+}
+
+extern "C" {
+	fn xcb_send_request(
+		c: *mut xcb::ffi::xcb_connection_t,
+		flags: c_int,
+		vector: *mut iovec,
+		request: *const ProtocolRequest,
+	) -> c_uint;
+	fn xcb_wait_for_reply(
+		c: *mut xcb::ffi::xcb_connection_t,
+		request: c_uint,
+		e: *mut *mut xcb::ffi::xcb_generic_error_t,
+	) -> *mut c_void;
+}
+
+const XCB_REQUEST_CHECKED: c_int = 1;
+
+/// An extension as libxcb identifies it. libxcb caches the extension's opcode per connection under an id it assigns
+/// on first use, so these live in statics like the extension ids of the generated C bindings.
+pub(crate) struct Extension(UnsafeCell<RawExtension>);
+
+// libxcb only writes the id while holding its own lock
+unsafe impl Sync for Extension {}
+
+impl Extension {
+	/// `name` is the extension's protocol name and must end with a nul byte.
+	pub(crate) const fn new(name: &'static [u8]) -> Self {
+		Extension(UnsafeCell::new(RawExtension {
+			name: name.as_ptr() as *const c_char,
+			global_id: 0,
+		}))
+	}
+
+	/// Gets the extension's major opcode, or `None` if the server doesn't have it. Sending a request for a missing
+	/// extension would shut the connection down, so this has to be checked first.
+	pub(crate) fn major_opcode(&'static self, conn: &xcb::Connection) -> Option<u8> {
+		unsafe {
+			let data = xcb::ffi::xcb_get_extension_data(conn.get_raw_conn(), self.0.get() as *mut xcb::ffi::xcb_extension_t);
+			if data.is_null() || (*data).present == 0 {
+				None
+			} else {
+				Some((*data).major_opcode)
+			}
+		}
+	}
+
+	/// Sends a request that has no reply. `request` starts with the 4-byte request header, which libxcb fills in.
+	pub(crate) fn send(&'static self, conn: &xcb::Connection, opcode: u8, request: &mut [u8]) {
+		self.send_request(conn, opcode, request, false);
+	}
+
+	/// Sends a request and waits for its reply, which is returned whole, header included. Returns `None` if the server
+	/// responded with an error.
+	pub(crate) fn send_with_reply(&'static self, conn: &xcb::Connection, opcode: u8, request: &mut [u8]) -> Option<Vec<u8>> {
+		let sequence = self.send_request(conn, opcode, request, true);
+		unsafe {
+			let mut error = ptr::null_mut();
+			let reply = xcb_wait_for_reply(conn.get_raw_conn(), sequence, &mut error);
+			libc::free(error as *mut c_void);
+			if reply.is_null() {
+				return None;
+			}
+			// Replies are 32 bytes plus the length field's count of 4-byte units
+			let extra_len = ptr::read_unaligned((reply as *const u8).add(4) as *const u32);
+			let len = 32 + extra_len as usize * 4;
+			let bytes = std::slice::from_raw_parts(reply as *const u8, len).to_vec();
+			libc::free(reply);
+			Some(bytes)
+		}
+	}
+
+	fn send_request(&'static self, conn: &xcb::Connection, opcode: u8, request: &mut [u8], has_reply: bool) -> c_uint {
+		// libxcb needs two spare entries in front of the request data for its own use
+		let mut vector = [
+			iovec {
+				iov_base: ptr::null_mut(),
+				iov_len: 0,
+			},
+			iovec {
+				iov_base: ptr::null_mut(),
+				iov_len: 0,
+			},
+			iovec {
+				iov_base: request.as_mut_ptr() as *mut c_void,
+				iov_len: request.len(),
+			},
+		];
+		let protocol_request = ProtocolRequest {
+			count: 1,
+			ext: self.0.get(),
+			opcode,
+			isvoid: !has_reply as u8,
+		};
+		let flags = if has_reply { XCB_REQUEST_CHECKED } else { 0 };
+		unsafe { xcb_send_request(conn.get_raw_conn(), flags, vector.as_mut_ptr().add(2), &protocol_request) }
+	}
+}
@@ -0,0 +1,109 @@
+use crate::event::Modifiers;
+use crate::window::xcb::XcbBackendError;
+
+const NO_SYMBOL: xcb::Keysym = 0;
+
+/// The server's mapping from keycodes to keysyms, as returned by `GetKeyboardMapping`.
+pub(crate) struct KeyboardMapping {
+	min_keycode: xcb::Keycode,
+	keysyms_per_keycode: usize,
+	keysyms: Vec<xcb::Keysym>,
+}
+
+impl KeyboardMapping {
+	pub fn load(conn: &xcb::Connection) -> Result<Self, XcbBackendError> {
+		let setup = conn.get_setup();
+		let min_keycode = setup.min_keycode();
+		let count = setup.max_keycode() - min_keycode + 1;
+		let reply = xcb::get_keyboard_mapping(conn, min_keycode, count).get_reply().map_err(|e| {
+			log::error!("Failed to get keyboard mapping: {}", e);
+			XcbBackendError::Unknown
+		})?;
+		Ok(KeyboardMapping {
+			min_keycode,
+			keysyms_per_keycode: reply.keysyms_per_keycode() as usize,
+			keysyms: reply.keysyms().to_vec(),
+		})
+	}
+
+	/// Resolves a keycode into a keysym using the first group of the mapping, following the core protocol rules for
+	/// Shift, Caps Lock and Num Lock.
+	pub fn keysym(&self, keycode: xcb::Keycode, modifiers: Modifiers) -> Option<xcb::Keysym> {
+		let index = keycode.checked_sub(self.min_keycode)? as usize * self.keysyms_per_keycode;
+		let syms = self.keysyms.get(index..index + self.keysyms_per_keycode.min(2))?;
+		let lower = syms.get(0).cloned().unwrap_or(NO_SYMBOL);
+		let upper = match syms.get(1).cloned().unwrap_or(NO_SYMBOL) {
+			NO_SYMBOL => to_upper(lower),
+			upper => upper,
+		};
+
+		let keysym = if modifiers.num_lock && is_keypad(upper) {
+			if modifiers.shift {
+				lower
+			} else {
+				upper
+			}
+		} else if modifiers.shift != (modifiers.caps_lock && is_letter(lower)) {
+			upper
+		} else {
+			lower
+		};
+		match keysym {
+			NO_SYMBOL => None,
+			keysym => Some(keysym),
+		}
+	}
+}
+
+fn is_letter(keysym: xcb::Keysym) -> bool {
+	(0x61..=0x7a).contains(&keysym) || (0x41..=0x5a).contains(&keysym)
+}
+
+fn is_keypad(keysym: xcb::Keysym) -> bool {
+	(0xff80..=0xffbd).contains(&keysym)
+}
+
+fn to_upper(keysym: xcb::Keysym) -> xcb::Keysym {
+	if (0x61..=0x7a).contains(&keysym) {
+		keysym - 0x20
+	} else {
+		keysym
+	}
+}
+
+#[test]
+fn us_layout_a_key_resolves() {
+	// A US layout maps keycode 38 to a/A, keycode 10 to 1/exclam and keycode 87 to KP_End/KP_1
+	let mut keysyms = vec![NO_SYMBOL; (88 - 8) * 2];
+	keysyms[(38 - 8) * 2] = 0x61;
+	keysyms[(38 - 8) * 2 + 1] = 0x41;
+	keysyms[(10 - 8) * 2] = 0x31;
+	keysyms[(10 - 8) * 2 + 1] = 0x21;
+	keysyms[(87 - 8) * 2] = 0xff9c;
+	keysyms[(87 - 8) * 2 + 1] = 0xffb1;
+	let mapping = KeyboardMapping {
+		min_keycode: 8,
+		keysyms_per_keycode: 2,
+		keysyms,
+	};
+	let shift = Modifiers {
+		shift: true,
+		..Modifiers::default()
+	};
+	let caps_lock = Modifiers {
+		caps_lock: true,
+		..Modifiers::default()
+	};
+	let num_lock = Modifiers {
+		num_lock: true,
+		..Modifiers::default()
+	};
+
+	assert_eq!(mapping.keysym(38, Modifiers::default()), Some(0x61));
+	assert_eq!(mapping.keysym(38, shift), Some(0x41));
+	assert_eq!(mapping.keysym(38, caps_lock), Some(0x41));
+	assert_eq!(mapping.keysym(10, caps_lock), Some(0x31));
+	assert_eq!(mapping.keysym(87, Modifiers::default()), Some(0xff9c));
+	assert_eq!(mapping.keysym(87, num_lock), Some(0xffb1));
+	assert_eq!(mapping.keysym(12, Modifiers::default()), None);
+}
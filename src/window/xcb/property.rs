@@ -6,15 +6,19 @@ pub enum XPropertyType {
 	Latin1String,
 	Utf8String,
 	Cardinal,
+	WmSizeHints,
+	MotifWmHints,
 }
 
 impl XPropertyType {
-	pub fn atom(self, backend: &XcbBackend) -> xcb::Atom {
+	pub fn atom(self, backend: &XcbBackend) -> Result<xcb::Atom, XcbBackendError> {
 		match self {
-			XPropertyType::Atom => xcb::ATOM_ATOM,
-			XPropertyType::Latin1String => xcb::ATOM_STRING,
-			XPropertyType::Utf8String => backend.intern_atom("UTF8_STRING").unwrap(),
-			XPropertyType::Cardinal => xcb::ATOM_CARDINAL,
+			XPropertyType::Atom => Ok(xcb::ATOM_ATOM),
+			XPropertyType::Latin1String => Ok(xcb::ATOM_STRING),
+			XPropertyType::Utf8String => backend.intern_atom("UTF8_STRING"),
+			XPropertyType::Cardinal => Ok(xcb::ATOM_CARDINAL),
+			XPropertyType::WmSizeHints => Ok(xcb::ATOM_WM_SIZE_HINTS),
+			XPropertyType::MotifWmHints => backend.intern_atom("_MOTIF_WM_HINTS"),
 		}
 	}
 }
@@ -93,9 +97,35 @@ pub trait XProperty<F: XPropertyFormat>: Sized {
 		target_length: u32,
 	) -> Result<Vec<Self>, XcbBackendError>;
 
+	/// Decodes a property that was read in several chunks, as with the INCR protocol. By default each chunk is decoded
+	/// on its own; types whose values can be split across chunks should join the raw data first.
+	fn from_property_replies(
+		backend: &XcbBackend,
+		replies: Vec<xcb::GetPropertyReply>,
+		target_offset: u32,
+		target_length: u32,
+	) -> Result<Vec<Self>, XcbBackendError> {
+		let mut values = Vec::new();
+		for reply in replies {
+			values.extend(Self::from_property_reply(backend, reply, target_offset, target_length)?);
+		}
+		Ok(values)
+	}
+
 	fn to_property_value(backend: &XcbBackend, values: Vec<Self>) -> Result<Vec<F>, XcbBackendError>;
 }
 
+/// Joins the byte data of property chunks.
+fn concat_bytes(replies: &[xcb::GetPropertyReply]) -> Vec<u8> {
+	replies.iter().flat_map(|reply| reply.value::<u8>().iter().cloned()).collect()
+}
+
+/// A 32-bit `CARDINAL` value.
+///
+/// Format-32 property data is always 32 bits per item on the wire. Xlib hands it to clients as an array of C `long`s,
+/// which are 64 bits wide on LP64 platforms, so code ported from Xlib often looks like it reads 64-bit values when only
+/// the low 32 bits of each `long` are ever set. XCB returns the items as they are sent, so this type never truncates.
+/// Properties that really hold 64-bit values split them over two items; use `Cardinal64Property` for those.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CardinalProperty(pub u32);
 
@@ -111,9 +141,10 @@ impl XProperty<u32> for CardinalProperty {
 		_target_length: u32,
 	) -> Result<Vec<Self>, XcbBackendError> {
 		let value = reply.value::<u32>();
-		if reply.type_() != Self::property_type().atom(backend) {
+		let expected = Self::property_type().atom(backend)?;
+		if reply.type_() != expected {
 			return Err(XcbBackendError::PropertyTypeMismatch {
-				expected: Self::property_type().atom(backend),
+				expected,
 				found: reply.type_(),
 			});
 		}
@@ -125,6 +156,67 @@ impl XProperty<u32> for CardinalProperty {
 	}
 }
 
+/// A 64-bit `CARDINAL` value stored as two 32-bit items, low word first, as done for 64-bit client data such as the
+/// sync counter values of `_NET_WM_SYNC_REQUEST`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cardinal64Property(pub u64);
+
+impl Cardinal64Property {
+	/// Reassembles pairs of 32-bit words into values, or returns `None` if a word is left over.
+	fn from_words(words: &[u32]) -> Option<Vec<Self>> {
+		if words.len() % 2 != 0 {
+			return None;
+		}
+		Some(
+			words
+				.chunks_exact(2)
+				.map(|pair| Cardinal64Property(u64::from(pair[1]) << 32 | u64::from(pair[0])))
+				.collect(),
+		)
+	}
+}
+
+impl XProperty<u32> for Cardinal64Property {
+	fn property_type() -> XPropertyType {
+		XPropertyType::Cardinal
+	}
+
+	fn from_property_reply(
+		backend: &XcbBackend,
+		reply: xcb::GetPropertyReply,
+		_target_offset: u32,
+		_target_length: u32,
+	) -> Result<Vec<Self>, XcbBackendError> {
+		let expected = Self::property_type().atom(backend)?;
+		if reply.type_() != expected {
+			return Err(XcbBackendError::PropertyTypeMismatch {
+				expected,
+				found: reply.type_(),
+			});
+		}
+		Self::from_words(reply.value::<u32>()).ok_or_else(|| {
+			log::error!("64-bit cardinal property has an odd number of 32-bit items");
+			XcbBackendError::PropertyEncodingError
+		})
+	}
+
+	fn to_property_value(_backend: &XcbBackend, values: Vec<Self>) -> Result<Vec<u32>, XcbBackendError> {
+		Ok(values
+			.into_iter()
+			.flat_map(|cardinal| vec![cardinal.0 as u32, (cardinal.0 >> 32) as u32])
+			.collect())
+	}
+}
+
+#[test]
+fn cardinal64_words_are_reassembled() {
+	assert_eq!(
+		Cardinal64Property::from_words(&[0x9abc_def0, 0x1234_5678, 7, 0]),
+		Some(vec![Cardinal64Property(0x1234_5678_9abc_def0), Cardinal64Property(7)])
+	);
+	assert_eq!(Cardinal64Property::from_words(&[1, 2, 3]), None);
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AtomProperty(pub xcb::Atom);
 
@@ -140,9 +232,10 @@ impl XProperty<u32> for AtomProperty {
 		_target_length: u32,
 	) -> Result<Vec<Self>, XcbBackendError> {
 		let value = reply.value::<u32>();
-		if reply.type_() != Self::property_type().atom(backend) {
+		let expected = Self::property_type().atom(backend)?;
+		if reply.type_() != expected {
 			return Err(XcbBackendError::PropertyTypeMismatch {
-				expected: Self::property_type().atom(backend),
+				expected,
 				found: reply.type_(),
 			});
 		}
@@ -165,18 +258,16 @@ impl XProperty<u8> for String {
 		_target_offset: u32,
 		_target_length: u32,
 	) -> Result<Vec<Self>, XcbBackendError> {
-		let value = reply.value::<u8>();
-		log::debug!("Getting Latin1 strings with length {}", value.len());
-		value
-			.split(|b| *b == 0u8)
-			.filter(|s| !s.is_empty())
-			.map(|s| {
-				String::from_utf8(s.to_owned()).map_err(|e| {
-					log::error!("Error while decoding UTF8 String property: {}", e);
-					XcbBackendError::PropertyEncodingError
-				})
-			})
-			.collect()
+		utf8_strings(reply.value::<u8>())
+	}
+
+	fn from_property_replies(
+		_backend: &XcbBackend,
+		replies: Vec<xcb::GetPropertyReply>,
+		_target_offset: u32,
+		_target_length: u32,
+	) -> Result<Vec<Self>, XcbBackendError> {
+		utf8_strings(&concat_bytes(&replies))
 	}
 
 	fn to_property_value(_backend: &XcbBackend, values: Vec<Self>) -> Result<Vec<u8>, XcbBackendError> {
@@ -189,6 +280,21 @@ impl XProperty<u8> for String {
 	}
 }
 
+/// Splits NUL-separated UTF-8 strings.
+fn utf8_strings(value: &[u8]) -> Result<Vec<String>, XcbBackendError> {
+	log::debug!("Getting Latin1 strings with length {}", value.len());
+	value
+		.split(|b| *b == 0u8)
+		.filter(|s| !s.is_empty())
+		.map(|s| {
+			String::from_utf8(s.to_owned()).map_err(|e| {
+				log::error!("Error while decoding UTF8 String property: {}", e);
+				XcbBackendError::PropertyEncodingError
+			})
+		})
+		.collect()
+}
+
 pub struct Latin1String {
 	pub data: Vec<u8>,
 }
@@ -210,13 +316,16 @@ impl XProperty<u8> for Latin1String {
 		_target_offset: u32,
 		_target_length: u32,
 	) -> Result<Vec<Self>, XcbBackendError> {
-		let value = reply.value::<u8>();
-		log::debug!("Getting UTF-8 strings property with length {}", value.len());
-		Ok(value
-			.split(|b| *b == 0u8)
-			.filter(|s| !s.is_empty())
-			.map(|s| Latin1String { data: s.to_owned() })
-			.collect())
+		Ok(latin1_strings(reply.value::<u8>()))
+	}
+
+	fn from_property_replies(
+		_backend: &XcbBackend,
+		replies: Vec<xcb::GetPropertyReply>,
+		_target_offset: u32,
+		_target_length: u32,
+	) -> Result<Vec<Self>, XcbBackendError> {
+		Ok(latin1_strings(&concat_bytes(&replies)))
 	}
 
 	fn to_property_value(_backend: &XcbBackend, values: Vec<Self>) -> Result<Vec<u8>, XcbBackendError> {
@@ -228,3 +337,193 @@ impl XProperty<u8> for Latin1String {
 		Ok(buf)
 	}
 }
+
+/// Splits NUL-separated Latin-1 strings.
+fn latin1_strings(value: &[u8]) -> Vec<Latin1String> {
+	log::debug!("Getting UTF-8 strings property with length {}", value.len());
+	value
+		.split(|b| *b == 0u8)
+		.filter(|s| !s.is_empty())
+		.map(|s| Latin1String { data: s.to_owned() })
+		.collect()
+}
+
+/// The ICCCM `WM_SIZE_HINTS` structure, used for the `WM_NORMAL_HINTS` property. Only the fields whose flag is set in
+/// `flags` are used by the window manager.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WmSizeHints {
+	pub flags: u32,
+	pub x: i32,
+	pub y: i32,
+	pub width: u32,
+	pub height: u32,
+	pub min_width: u32,
+	pub min_height: u32,
+	pub max_width: u32,
+	pub max_height: u32,
+	pub width_inc: u32,
+	pub height_inc: u32,
+	pub min_aspect: (u32, u32),
+	pub max_aspect: (u32, u32),
+	pub base_width: u32,
+	pub base_height: u32,
+	pub win_gravity: u32,
+}
+
+impl WmSizeHints {
+	pub const US_POSITION: u32 = 1;
+	pub const US_SIZE: u32 = 1 << 1;
+	pub const P_POSITION: u32 = 1 << 2;
+	pub const P_SIZE: u32 = 1 << 3;
+	pub const P_MIN_SIZE: u32 = 1 << 4;
+	pub const P_MAX_SIZE: u32 = 1 << 5;
+	pub const P_RESIZE_INC: u32 = 1 << 6;
+	pub const P_ASPECT: u32 = 1 << 7;
+	pub const P_BASE_SIZE: u32 = 1 << 8;
+	pub const P_WIN_GRAVITY: u32 = 1 << 9;
+
+	/// The number of 32-bit values in the property.
+	const LEN: usize = 18;
+}
+
+impl XProperty<u32> for WmSizeHints {
+	fn property_type() -> XPropertyType {
+		XPropertyType::WmSizeHints
+	}
+
+	fn from_property_reply(
+		backend: &XcbBackend,
+		reply: xcb::GetPropertyReply,
+		_target_offset: u32,
+		_target_length: u32,
+	) -> Result<Vec<Self>, XcbBackendError> {
+		let expected = Self::property_type().atom(backend)?;
+		if reply.type_() != expected {
+			return Err(XcbBackendError::PropertyTypeMismatch {
+				expected,
+				found: reply.type_(),
+			});
+		}
+		Ok(reply
+			.value::<u32>()
+			.chunks_exact(Self::LEN)
+			.map(|v| WmSizeHints {
+				flags: v[0],
+				x: v[1] as i32,
+				y: v[2] as i32,
+				width: v[3],
+				height: v[4],
+				min_width: v[5],
+				min_height: v[6],
+				max_width: v[7],
+				max_height: v[8],
+				width_inc: v[9],
+				height_inc: v[10],
+				min_aspect: (v[11], v[12]),
+				max_aspect: (v[13], v[14]),
+				base_width: v[15],
+				base_height: v[16],
+				win_gravity: v[17],
+			})
+			.collect())
+	}
+
+	fn to_property_value(_backend: &XcbBackend, values: Vec<Self>) -> Result<Vec<u32>, XcbBackendError> {
+		Ok(values
+			.into_iter()
+			.flat_map(|hints| {
+				vec![
+					hints.flags,
+					hints.x as u32,
+					hints.y as u32,
+					hints.width,
+					hints.height,
+					hints.min_width,
+					hints.min_height,
+					hints.max_width,
+					hints.max_height,
+					hints.width_inc,
+					hints.height_inc,
+					hints.min_aspect.0,
+					hints.min_aspect.1,
+					hints.max_aspect.0,
+					hints.max_aspect.1,
+					hints.base_width,
+					hints.base_height,
+					hints.win_gravity,
+				]
+			})
+			.collect())
+	}
+}
+
+/// The Motif `_MOTIF_WM_HINTS` structure, which most window managers still read to decide whether to decorate a window.
+/// Only the fields whose flag is set in `flags` are used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MotifWmHints {
+	pub flags: u32,
+	pub functions: u32,
+	pub decorations: u32,
+	pub input_mode: i32,
+	pub status: u32,
+}
+
+impl MotifWmHints {
+	pub const FUNCTIONS: u32 = 1;
+	pub const DECORATIONS: u32 = 1 << 1;
+	pub const INPUT_MODE: u32 = 1 << 2;
+	pub const STATUS: u32 = 1 << 3;
+
+	/// In `decorations`, enables all decorations.
+	pub const DECOR_ALL: u32 = 1;
+
+	/// The number of 32-bit values in the property.
+	const LEN: usize = 5;
+}
+
+impl XProperty<u32> for MotifWmHints {
+	fn property_type() -> XPropertyType {
+		XPropertyType::MotifWmHints
+	}
+
+	fn from_property_reply(
+		backend: &XcbBackend,
+		reply: xcb::GetPropertyReply,
+		_target_offset: u32,
+		_target_length: u32,
+	) -> Result<Vec<Self>, XcbBackendError> {
+		let expected = Self::property_type().atom(backend)?;
+		if reply.type_() != expected {
+			return Err(XcbBackendError::PropertyTypeMismatch {
+				expected,
+				found: reply.type_(),
+			});
+		}
+		Ok(reply
+			.value::<u32>()
+			.chunks_exact(Self::LEN)
+			.map(|v| MotifWmHints {
+				flags: v[0],
+				functions: v[1],
+				decorations: v[2],
+				input_mode: v[3] as i32,
+				status: v[4],
+			})
+			.collect())
+	}
+
+	fn to_property_value(_backend: &XcbBackend, values: Vec<Self>) -> Result<Vec<u32>, XcbBackendError> {
+		Ok(values
+			.into_iter()
+			.flat_map(|hints| {
+				vec![
+					hints.flags,
+					hints.functions,
+					hints.decorations,
+					hints.input_mode as u32,
+					hints.status,
+				]
+			})
+			.collect())
+	}
+}
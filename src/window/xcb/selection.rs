@@ -0,0 +1,246 @@
+use crate::window::xcb::{XcbBackend, XcbBackendError};
+use std::time::{Duration, Instant};
+
+/// How long to wait for the selection owner to convert the selection before giving up on it.
+const CONVERT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The property on the selection window that selection owners are asked to write the converted selection to.
+const SELECTION_PROPERTY: &str = "RAW_BRASS_SELECTION";
+
+impl XcbBackend {
+	/// Gets the text on the clipboard, or `None` if the clipboard is empty or its owner can't provide it as text. Events
+	/// that arrive while waiting for the clipboard owner are kept for the next `get_window_events`.
+	pub fn get_clipboard_text(&self) -> Result<Option<String>, XcbBackendError> {
+		let conn = self.conn.as_ref();
+		let clipboard_atom = self.intern_atom("CLIPBOARD")?;
+		let utf8_atom = self.intern_atom("UTF8_STRING")?;
+		let property_atom = self.intern_atom(SELECTION_PROPERTY)?;
+		let window = self.selection_window()?;
+
+		let owner = xcb::get_selection_owner(conn, clipboard_atom)
+			.get_reply()
+			.map_err(|_| XcbBackendError::Unknown)?
+			.owner();
+		if owner == xcb::NONE {
+			return Ok(None);
+		}
+		// Converting our own selection would wait on events that only get answered after we return
+		if owner == window {
+			return Ok(self.clipboard_text.borrow().clone());
+		}
+
+		xcb::convert_selection(conn, window, clipboard_atom, utf8_atom, property_atom, xcb::CURRENT_TIME);
+		conn.flush();
+		let property = self.wait_for_selection_notify(window, clipboard_atom)?;
+		if property == xcb::NONE {
+			// The owner couldn't convert the selection to UTF-8
+			return Ok(None);
+		}
+
+		// Reads the whole property, following the INCR protocol if the owner uses it
+		let text = self.get_property::<u8, String>(window, property, utf8_atom, 0, 0);
+		xcb::delete_property(conn, window, property);
+		Ok(Some(text?.concat()))
+	}
+
+	/// Puts `text` on the clipboard. It's served to other clients from `get_window_events` until another client takes
+	/// over the clipboard, so events have to keep being polled for pasting to work.
+	pub fn set_clipboard_text(&self, text: String) -> Result<(), XcbBackendError> {
+		let conn = self.conn.as_ref();
+		let clipboard_atom = self.intern_atom("CLIPBOARD")?;
+		let window = self.selection_window()?;
+
+		self.clipboard_text.replace(Some(text));
+		xcb::set_selection_owner(conn, window, clipboard_atom, xcb::CURRENT_TIME);
+		let owner = xcb::get_selection_owner(conn, clipboard_atom)
+			.get_reply()
+			.map_err(|_| XcbBackendError::Unknown)?
+			.owner();
+		if owner != window {
+			self.clipboard_text.replace(None);
+			return Err(XcbBackendError::Other("Failed to take ownership of the clipboard".to_owned()));
+		}
+		Ok(())
+	}
+
+	/// Answers another client's request for the clipboard's contents.
+	pub(crate) fn handle_selection_request(&self, request: &xcb::SelectionRequestEvent) -> Result<(), XcbBackendError> {
+		let conn = self.conn.as_ref();
+		// Obsolete clients don't name a property, in which case the target is used
+		let property = if request.property() == xcb::NONE {
+			request.target()
+		} else {
+			request.property()
+		};
+		let property = match self.convert_clipboard(request.requestor(), request.target(), property) {
+			Ok(()) => property,
+			Err(e) => {
+				log::debug!("Refusing selection request for target {}: {:?}", request.target(), e);
+				xcb::NONE
+			}
+		};
+
+		let notify = xcb::SelectionNotifyEvent::new(
+			request.time(),
+			request.requestor(),
+			request.selection(),
+			request.target(),
+			property,
+		);
+		xcb::send_event(conn, false, request.requestor(), 0, &notify);
+		conn.flush();
+		Ok(())
+	}
+
+	/// Forgets the clipboard text once another client owns the clipboard.
+	pub(crate) fn handle_selection_clear(&self, clear: &xcb::SelectionClearEvent) -> Result<(), XcbBackendError> {
+		if clear.selection() == self.intern_atom("CLIPBOARD")? {
+			self.clipboard_text.replace(None);
+		}
+		Ok(())
+	}
+
+	/// Writes the clipboard text to `property` on `requestor` in the format named by `target`.
+	fn convert_clipboard(&self, requestor: xcb::Window, target: xcb::Atom, property: xcb::Atom) -> Result<(), XcbBackendError> {
+		// The length of the ChangeProperty request header in 4-byte units
+		const CHANGE_PROPERTY_HEADER_LEN: usize = 6;
+
+		let conn = self.conn.as_ref();
+		let clipboard_text = self.clipboard_text.borrow();
+		let text = clipboard_text.as_ref().ok_or(XcbBackendError::Unknown)?;
+		let targets_atom = self.intern_atom("TARGETS")?;
+		let utf8_atom = self.intern_atom("UTF8_STRING")?;
+
+		if target == targets_atom {
+			let targets = [targets_atom, utf8_atom, xcb::ATOM_STRING];
+			xcb::change_property(
+				conn,
+				xcb::PROP_MODE_REPLACE as u8,
+				requestor,
+				property,
+				xcb::ATOM_ATOM,
+				32,
+				&targets,
+			);
+			return Ok(());
+		}
+
+		let data = if target == utf8_atom {
+			text.as_bytes().to_vec()
+		} else if target == xcb::ATOM_STRING {
+			text.chars()
+				.map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' })
+				.collect()
+		} else {
+			return Err(XcbBackendError::Unsupported("clipboard target"));
+		};
+		// Sending more than fits in one request would need the INCR protocol
+		let max_request_bytes = (self.conn.get_maximum_request_length() as usize).saturating_sub(CHANGE_PROPERTY_HEADER_LEN) * 4;
+		if data.len() > max_request_bytes {
+			return Err(XcbBackendError::RequestTooLarge);
+		}
+		xcb::change_property(conn, xcb::PROP_MODE_REPLACE as u8, requestor, property, target, 8, &data);
+		Ok(())
+	}
+
+	/// Waits for the selection owner's answer to a conversion request, returning the property the selection was written to,
+	/// or `xcb::NONE` if it couldn't be converted.
+	fn wait_for_selection_notify(&self, window: xcb::Window, selection: xcb::Atom) -> Result<xcb::Atom, XcbBackendError> {
+		let deadline = Instant::now() + CONVERT_TIMEOUT;
+		loop {
+			match self.conn.poll_for_event() {
+				Some(event) if event.response_type() & !0x80 == xcb::SELECTION_NOTIFY => {
+					let notify = unsafe { xcb::cast_event::<xcb::SelectionNotifyEvent>(&event) };
+					if notify.requestor() == window && notify.selection() == selection {
+						return Ok(notify.property());
+					}
+				}
+				Some(event) => self.deferred_events.borrow_mut().push_back(event),
+				None if Instant::now() >= deadline => {
+					return Err(XcbBackendError::Other("Timed out waiting for the clipboard owner".to_owned()));
+				}
+				None => std::thread::sleep(Duration::from_millis(1)),
+			}
+		}
+	}
+
+	/// Reads a property being transferred with the INCR protocol, after the initial `INCR` property has been found on it.
+	/// Returns the chunks in the order they were sent.
+	pub(crate) fn read_incr_property(
+		&self,
+		window: xcb::Window,
+		property: xcb::Atom,
+		property_type: xcb::Atom,
+	) -> Result<Vec<xcb::GetPropertyReply>, XcbBackendError> {
+		let conn = self.conn.as_ref();
+		// Deleting the INCR property tells the owner to start sending, and deleting each chunk asks for the next one
+		xcb::delete_property(conn, window, property);
+		conn.flush();
+		let mut chunks = Vec::new();
+		loop {
+			self.wait_for_new_property_value(window, property)?;
+			let chunk = xcb::get_property(conn, true, window, property, property_type, 0, std::u32::MAX / 4)
+				.get_reply()
+				.map_err(|_| XcbBackendError::Unknown)?;
+			conn.flush();
+			if chunk.value_len() == 0 {
+				return Ok(chunks);
+			}
+			chunks.push(chunk);
+		}
+	}
+
+	/// Waits until a new value is written to `property` on `window`, deferring other events like
+	/// `wait_for_selection_notify`.
+	fn wait_for_new_property_value(&self, window: xcb::Window, property: xcb::Atom) -> Result<(), XcbBackendError> {
+		let deadline = Instant::now() + CONVERT_TIMEOUT;
+		loop {
+			match self.conn.poll_for_event() {
+				Some(event) if event.response_type() & !0x80 == xcb::PROPERTY_NOTIFY => {
+					let notify = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) };
+					if notify.window() == window && notify.atom() == property && notify.state() == xcb::PROPERTY_NEW_VALUE as u8 {
+						return Ok(());
+					}
+				}
+				Some(event) => self.deferred_events.borrow_mut().push_back(event),
+				None if Instant::now() >= deadline => {
+					return Err(XcbBackendError::Other(
+						"Timed out waiting for an incremental transfer".to_owned(),
+					));
+				}
+				None => std::thread::sleep(Duration::from_millis(1)),
+			}
+		}
+	}
+
+	/// Gets the invisible window used to own and receive selections, creating it on first use.
+	fn selection_window(&self) -> Result<xcb::Window, XcbBackendError> {
+		if let Some(window) = self.selection_window.get() {
+			return Ok(window);
+		}
+		let conn = self.conn.as_ref();
+		let window = conn.generate_id();
+		xcb::create_window_checked(
+			conn,
+			xcb::COPY_FROM_PARENT as u8,
+			window,
+			self.get_screen().root(),
+			0,
+			0,
+			1,
+			1,
+			0,
+			xcb::WINDOW_CLASS_INPUT_ONLY as u16,
+			xcb::COPY_FROM_PARENT,
+			// Property changes drive incremental transfers
+			&[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)],
+		)
+		.request_check()
+		.map_err(|e| {
+			log::error!("Failed to create selection window: {}", e);
+			XcbBackendError::Unknown
+		})?;
+		self.selection_window.set(Some(window));
+		Ok(window)
+	}
+}
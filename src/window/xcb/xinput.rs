@@ -0,0 +1,358 @@
+use crate::event::StylusState;
+use crate::window::xcb::ext::Extension;
+use crate::window::xcb::XcbBackend;
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+// The XInput code generated by the xcb bindings doesn't compile, so the few XInput2 requests needed for stylus state
+// are built here. Raw events are selected on the root window because they're reported for every device along with
+// their valuators, and the server sends them right before the core events they cause.
+
+/// The X Input Extension, which carries XInput2.
+static XINPUT: Extension = Extension::new(b"XInputExtension\0");
+
+const XI_SELECT_EVENTS: u8 = 46;
+const XI_QUERY_VERSION: u8 = 47;
+const XI_QUERY_DEVICE: u8 = 48;
+
+const XI_ALL_DEVICES: u16 = 0;
+const XI_ALL_MASTER_DEVICES: u16 = 1;
+
+const XI_DEVICE_CHANGED: u16 = 1;
+const XI_HIERARCHY_CHANGED: u16 = 11;
+const XI_RAW_BUTTON_PRESS: u16 = 15;
+const XI_RAW_BUTTON_RELEASE: u16 = 16;
+const XI_RAW_MOTION: u16 = 17;
+
+const VALUATOR_CLASS: u16 = 2;
+
+/// The valuator labels that identify stylus axes, as set by the X input drivers.
+const AXIS_LABELS: [&str; 3] = ["Abs Pressure", "Abs Tilt X", "Abs Tilt Y"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AxisKind {
+	Pressure,
+	TiltX,
+	TiltY,
+}
+
+/// A valuator of an input device that reports part of the stylus state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct StylusAxis {
+	number: u16,
+	kind: AxisKind,
+	min: f64,
+	max: f64,
+}
+
+impl StylusAxis {
+	/// Maps a value of the valuator onto the range `StylusState` uses for the axis.
+	fn normalize(&self, value: f64) -> f64 {
+		if self.max <= self.min {
+			return 0.0;
+		}
+		let fraction = ((value - self.min) / (self.max - self.min)).max(0.0).min(1.0);
+		match self.kind {
+			AxisKind::Pressure => fraction,
+			AxisKind::TiltX | AxisKind::TiltY => fraction * 2.0 - 1.0,
+		}
+	}
+}
+
+/// The valuator values of a raw event, by valuator number.
+#[derive(Debug, Clone, PartialEq)]
+struct RawEvent {
+	source: u16,
+	values: Vec<(u16, f64)>,
+}
+
+/// XInput2 state used to fill in the stylus state of pointer events.
+#[derive(Debug, Default)]
+pub(crate) struct StylusTracker {
+	/// The XInput major opcode, which its events are tagged with. `None` if XInput2 events aren't selected.
+	opcode: Option<u8>,
+	/// The atoms of `AXIS_LABELS`.
+	labels: Vec<xcb::Atom>,
+	/// The stylus axes of each physical device seen so far, by device id. Devices without any, like mice, have none.
+	axes: HashMap<u16, Vec<StylusAxis>>,
+	/// The last known stylus state of each device with stylus axes, since raw events only report the axes that changed.
+	states: HashMap<u16, StylusState>,
+	/// The stylus state of the device that sent the latest raw event, which belongs to the next pointer event.
+	current: Option<StylusState>,
+}
+
+impl StylusTracker {
+	/// Gets the stylus state for the pointer event being translated.
+	pub(crate) fn current(&self) -> Option<StylusState> {
+		self.current
+	}
+
+	/// Applies a raw event to its device's stylus state, which becomes the current one. Events from devices without
+	/// stylus axes clear it.
+	fn update(&mut self, event: &RawEvent) {
+		let axes = match self.axes.get(&event.source) {
+			Some(axes) if !axes.is_empty() => axes,
+			_ => {
+				self.current = None;
+				return;
+			}
+		};
+		let state = self.states.entry(event.source).or_insert(StylusState {
+			pressure: 0.0,
+			tilt: (0.0, 0.0),
+		});
+		for &(number, value) in &event.values {
+			if let Some(axis) = axes.iter().find(|axis| axis.number == number) {
+				let value = axis.normalize(value);
+				match axis.kind {
+					AxisKind::Pressure => state.pressure = value,
+					AxisKind::TiltX => state.tilt.0 = value,
+					AxisKind::TiltY => state.tilt.1 = value,
+				}
+			}
+		}
+		self.current = Some(*state);
+	}
+}
+
+impl XcbBackend {
+	/// Selects XInput2 raw pointer events so that pointer events carry the state of the stylus that caused them. This
+	/// requires XInput 2.1; without it pointer events have no stylus state.
+	pub(crate) fn select_stylus_events(&self) {
+		let opcode = match XINPUT.major_opcode(&self.conn) {
+			Some(opcode) => opcode,
+			None => return,
+		};
+		// The version has to be negotiated before any other XInput2 request is made
+		let mut request = [0; 8];
+		request[4..6].copy_from_slice(&2u16.to_ne_bytes());
+		request[6..8].copy_from_slice(&2u16.to_ne_bytes());
+		let supported = match XINPUT.send_with_reply(&self.conn, XI_QUERY_VERSION, &mut request) {
+			Some(reply) => (read_u16(&reply, 8), read_u16(&reply, 10)) >= (2, 1),
+			None => false,
+		};
+		if !supported {
+			log::info!("XInput 2.1 is not available, pointer events won't have stylus state");
+			return;
+		}
+		let labels = match AXIS_LABELS
+			.iter()
+			.map(|label| self.intern_atom(label))
+			.collect::<Result<Vec<_>, _>>()
+		{
+			Ok(labels) => labels,
+			Err(e) => {
+				log::error!("Failed to intern stylus axis labels: {:?}", e);
+				return;
+			}
+		};
+
+		// Hierarchy changes can only be selected for all devices, and raw events are only needed once per master device
+		let hierarchy_mask = 1u32 << XI_HIERARCHY_CHANGED;
+		let pointer_mask = [XI_DEVICE_CHANGED, XI_RAW_BUTTON_PRESS, XI_RAW_BUTTON_RELEASE, XI_RAW_MOTION]
+			.iter()
+			.fold(0u32, |mask, &event_type| mask | 1 << event_type);
+		let mut request = [0; 28];
+		request[4..8].copy_from_slice(&self.get_screen().root().to_ne_bytes());
+		request[8..10].copy_from_slice(&2u16.to_ne_bytes());
+		for (event_mask, &(device, mask)) in request[12..]
+			.chunks_mut(8)
+			.zip(&[(XI_ALL_DEVICES, hierarchy_mask), (XI_ALL_MASTER_DEVICES, pointer_mask)])
+		{
+			event_mask[0..2].copy_from_slice(&device.to_ne_bytes());
+			event_mask[2..4].copy_from_slice(&1u16.to_ne_bytes());
+			event_mask[4..8].copy_from_slice(&mask.to_ne_bytes());
+		}
+		XINPUT.send(&self.conn, XI_SELECT_EVENTS, &mut request);
+
+		let mut tracker = self.stylus.borrow_mut();
+		tracker.opcode = Some(opcode);
+		tracker.labels = labels;
+	}
+
+	/// Updates the stylus state from a generic event if it's an XInput2 one. Returns whether it was.
+	pub(crate) fn handle_xinput_event(&self, event: &xcb::GenericEvent) -> bool {
+		// libxcb moves the extra data of generic events behind the 32-byte event and its full sequence number
+		let bytes = unsafe {
+			let extra_len = (*(event.ptr as *const xcb::ffi::xcb_ge_generic_event_t)).length;
+			std::slice::from_raw_parts(event.ptr as *const u8, 36 + extra_len as usize * 4)
+		};
+		let mut tracker = self.stylus.borrow_mut();
+		if tracker.opcode != Some(bytes[1]) {
+			return false;
+		}
+
+		match read_u16(bytes, 8) {
+			XI_HIERARCHY_CHANGED => {
+				tracker.axes.clear();
+				tracker.states.clear();
+			}
+			XI_DEVICE_CHANGED => {
+				let source = read_u16(bytes, 18);
+				tracker.axes.remove(&source);
+				tracker.states.remove(&source);
+			}
+			XI_RAW_BUTTON_PRESS | XI_RAW_BUTTON_RELEASE | XI_RAW_MOTION => {
+				let raw_event = parse_raw_event(bytes);
+				if !tracker.axes.contains_key(&raw_event.source) {
+					let axes = self.query_stylus_axes(raw_event.source, &tracker.labels);
+					tracker.axes.insert(raw_event.source, axes);
+				}
+				tracker.update(&raw_event);
+			}
+			_ => {}
+		}
+		true
+	}
+
+	/// Finds the valuators of a device that report stylus state.
+	fn query_stylus_axes(&self, device: u16, labels: &[xcb::Atom]) -> Vec<StylusAxis> {
+		let mut request = [0; 8];
+		request[4..6].copy_from_slice(&device.to_ne_bytes());
+		match XINPUT.send_with_reply(&self.conn, XI_QUERY_DEVICE, &mut request) {
+			Some(reply) => parse_stylus_axes(&reply, labels),
+			None => Vec::new(),
+		}
+	}
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+	u16::from_ne_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+	u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Reads a 32.32 fixed point number, which XInput2 uses for valuator values.
+fn read_fp3232(bytes: &[u8], offset: usize) -> f64 {
+	read_u32(bytes, offset) as i32 as f64 + read_u32(bytes, offset + 4) as f64 / 4_294_967_296.0
+}
+
+/// Parses a raw event as laid out by libxcb. A mask of valuator numbers follows the event, then the values of the
+/// valuators in the mask.
+fn parse_raw_event(bytes: &[u8]) -> RawEvent {
+	let mask_len = read_u16(bytes, 22) as usize;
+	let mut values = Vec::new();
+	for number in 0..mask_len * 32 {
+		let offset = 36 + mask_len * 4 + values.len() * 8;
+		let in_mask = read_u32(bytes, 36 + number / 32 * 4) & (1 << (number % 32)) != 0;
+		if in_mask && offset + 8 <= bytes.len() {
+			values.push((number as u16, read_fp3232(bytes, offset)));
+		}
+	}
+	RawEvent {
+		source: read_u16(bytes, 20),
+		values,
+	}
+}
+
+/// Finds the valuator classes labelled as stylus axes in the classes of the device described by an XIQueryDevice reply.
+fn parse_stylus_axes(reply: &[u8], labels: &[xcb::Atom]) -> Vec<StylusAxis> {
+	let mut axes = Vec::new();
+	if reply.len() < 44 || read_u16(reply, 8) == 0 {
+		return axes;
+	}
+	let num_classes = read_u16(reply, 38);
+	let name_len = read_u16(reply, 40) as usize;
+	// The device's name is padded to 4 bytes
+	let mut offset = 44 + (name_len + 3) / 4 * 4;
+	for _ in 0..num_classes {
+		if offset + 4 > reply.len() {
+			break;
+		}
+		let class_len = read_u16(reply, offset + 2) as usize * 4;
+		if read_u16(reply, offset) == VALUATOR_CLASS && offset + 44 <= reply.len() {
+			let kind = match labels.iter().position(|&label| label == read_u32(reply, offset + 8)) {
+				Some(0) => Some(AxisKind::Pressure),
+				Some(1) => Some(AxisKind::TiltX),
+				Some(2) => Some(AxisKind::TiltY),
+				_ => None,
+			};
+			if let Some(kind) = kind {
+				axes.push(StylusAxis {
+					number: read_u16(reply, offset + 6),
+					kind,
+					min: read_fp3232(reply, offset + 12),
+					max: read_fp3232(reply, offset + 20),
+				});
+			}
+		}
+		if class_len == 0 {
+			break;
+		}
+		offset += class_len;
+	}
+	axes
+}
+
+#[test]
+fn stylus_state_is_read_from_valuators() {
+	const PRESSURE: xcb::Atom = 100;
+	const TILT_X: xcb::Atom = 101;
+	const TILT_Y: xcb::Atom = 102;
+	let labels = [PRESSURE, TILT_X, TILT_Y];
+
+	let valuator_class = |number: u16, label: xcb::Atom, min: i32, max: i32| {
+		let mut class = vec![0; 44];
+		class[0..2].copy_from_slice(&VALUATOR_CLASS.to_ne_bytes());
+		class[2..4].copy_from_slice(&11u16.to_ne_bytes());
+		class[6..8].copy_from_slice(&number.to_ne_bytes());
+		class[8..12].copy_from_slice(&label.to_ne_bytes());
+		class[12..16].copy_from_slice(&min.to_ne_bytes());
+		class[20..24].copy_from_slice(&max.to_ne_bytes());
+		class
+	};
+	// A tablet with x and y on valuators 0 and 1, which aren't stylus axes, then pressure and tilt
+	let mut reply = vec![0; 44];
+	reply[8..10].copy_from_slice(&1u16.to_ne_bytes());
+	reply[38..40].copy_from_slice(&5u16.to_ne_bytes());
+	reply[40..42].copy_from_slice(&6u16.to_ne_bytes());
+	reply.extend_from_slice(b"tablet\0\0");
+	reply.extend(valuator_class(0, 1, 0, 1000));
+	reply.extend(valuator_class(1, 2, 0, 1000));
+	reply.extend(valuator_class(2, PRESSURE, 0, 2048));
+	reply.extend(valuator_class(3, TILT_X, -64, 64));
+	reply.extend(valuator_class(4, TILT_Y, -64, 64));
+	let axes = parse_stylus_axes(&reply, &labels);
+	assert_eq!(axes.len(), 3);
+	assert_eq!(axes[0].kind, AxisKind::Pressure);
+	assert_eq!((axes[0].number, axes[0].min, axes[0].max), (2, 0.0, 2048.0));
+
+	// A raw motion event that moved the pen and changed its pressure and x tilt
+	let mut bytes = vec![0; 40];
+	bytes[8..10].copy_from_slice(&XI_RAW_MOTION.to_ne_bytes());
+	bytes[20..22].copy_from_slice(&7u16.to_ne_bytes());
+	bytes[22..24].copy_from_slice(&1u16.to_ne_bytes());
+	bytes[36..40].copy_from_slice(&0b1101u32.to_ne_bytes());
+	for &value in &[500, 512, 32] {
+		bytes.extend_from_slice(&(value as i32).to_ne_bytes());
+		bytes.extend_from_slice(&0u32.to_ne_bytes());
+	}
+	let raw_event = parse_raw_event(&bytes);
+	assert_eq!(
+		raw_event,
+		RawEvent {
+			source: 7,
+			values: vec![(0, 500.0), (2, 512.0), (3, 32.0)],
+		}
+	);
+
+	let mut tracker = StylusTracker::default();
+	tracker.axes.insert(7, axes);
+	tracker.axes.insert(8, Vec::new());
+	tracker.update(&raw_event);
+	assert_eq!(
+		tracker.current(),
+		Some(StylusState {
+			pressure: 0.25,
+			tilt: (0.5, 0.0),
+		})
+	);
+	// A mouse moving afterwards has no stylus state
+	tracker.update(&RawEvent {
+		source: 8,
+		values: vec![(0, 10.0)],
+	});
+	assert_eq!(tracker.current(), None);
+}